@@ -1,16 +1,17 @@
 use actix_cors::Cors;
-use actix_web::{middleware, App, HttpServer};
+use actix_web::{middleware, web, App, HttpServer};
 use dotenv::dotenv;
+use futures::future::join_all;
 use log::*;
 use satschain_indexer::{
     configs::Config,
     db::Database,
-    explorer::routes::configure,
-    rpc::{sync_chain, Rpc},
+    explorer::{handlers::ChainDatabases, routes::configure},
+    rpc::{audit_chain, rederive_chain, sync_chain, sync_logs, Rpc},
+    utils::shutdown::Shutdown,
 };
 use simple_logger::SimpleLogger;
-use std::{env, time::Duration};
-use tokio::time::sleep;
+use std::env;
 #[tokio::main()]
 async fn main() -> std::io::Result<()> {
     // load environment variables
@@ -21,18 +22,79 @@ async fn main() -> std::io::Result<()> {
     let explorer_server_host: String = env::var("EXPLORER_SERVER_HOST")
         .unwrap_or_else(|_| "0.0.0.0".to_string());
 
-    // set configuration
+    // set configuration, one per indexed chain
     let log = SimpleLogger::new().with_level(LevelFilter::Info);
-    let config = Config::new();
+    let configs = Config::all();
 
-    if config.debug {
+    if configs[0].debug {
         log.with_level(LevelFilter::Debug).init().unwrap();
     } else {
         log.init().unwrap();
     }
 
+    // stop on SIGINT/SIGTERM after committing the fetched blocks
+    let shutdown = Shutdown::listen();
+
+    // listend port for frontend, audits and rederivations exit once done and serve nothing
+    let mut server = None;
+    if !configs[0].audit && !configs[0].rederive {
+        let databases =
+            web::Data::new(ChainDatabases::new(&configs).await);
+
+        let t = HttpServer::new(move || {
+            App::new()
+                .app_data(databases.clone())
+                .wrap(Cors::permissive())
+                .wrap(middleware::Logger::default())
+                .configure(configure)
+        })
+        .bind(format!("{explorer_server_host}:{explorer_server_port}"))? // Bind server to localhost:8080
+        .disable_signals()
+        .run();
+        server = Some(t.handle());
+        tokio::spawn(t);
+    }
+
+    // index the chains concurrently, each one with its own rpcs, websocket and checkpoints
+    let chains = configs.into_iter().map(|config| {
+        let chain = config.chain.id;
+        let task = tokio::spawn(index_chain(config, shutdown.child()));
+        let shutdown = shutdown.clone();
+
+        // a panicking chain stops the others once they commit and fails the process
+        async move {
+            let panicked = task.await.is_err_and(|err| err.is_panic());
+
+            if panicked {
+                error!(
+                    "Indexing of chain {} panicked, shutting down.",
+                    chain
+                );
+                shutdown.request();
+            }
+
+            panicked
+        }
+    });
+
+    let panicked = join_all(chains).await.contains(&true);
+
+    if let Some(server) = server {
+        server.stop(true).await;
+    }
+
+    if panicked {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+async fn index_chain(config: Config, shutdown: Shutdown) {
+    info!("Indexing chain {} ({}).", config.chain.name, config.chain.id);
+
     // set rpc
-    let rpc = Rpc::new(&config).await;
+    let rpc = Rpc::new(&config, shutdown.clone()).await;
 
     let db = Database::new(
         config.db_host.clone(),
@@ -41,41 +103,58 @@ async fn main() -> std::io::Result<()> {
         config.db_name.clone(),
         config.chain.clone(),
     )
-    .await;
+    .await
+    .with_datasets(config.datasets.clone());
+
+    // drop the rows of batches interrupted by a crash before replaying them, the logs mode stores
+    // no blocks to mark its batches and only replays rows deduplicated by the tables
+    if config.log_filter.is_none() {
+        db.clean_uncommitted_data(
+            &config.sync_job,
+            config.range_end_block(),
+        )
+        .await;
+    }
+
+    if config.audit {
+        audit_chain(&rpc, &db, &config).await;
+        return;
+    }
+
+    if config.rederive {
+        rederive_chain(&db, &config).await;
+        return;
+    }
 
-    // get new blocks
-    if config.ws_url.is_some() && config.end_block == 0
-        || config.end_block == -1
+    // get new blocks, through the websocket when available or by polling the rpcs otherwise
+    let mut listener = None;
+    // the logs mode never stores full blocks, even with `--end-block -1`
+    if config.log_filter.is_none()
+        && ((config.ws_url.is_some() || config.new_blocks_only)
+            && config.end_block == 0
+            || config.end_block == -1)
     {
         info!("------ here is get new block parts ------");
-        tokio::spawn({
+        listener = Some(tokio::spawn({
             let rpc: Rpc = rpc.clone();
             let db: Database = db.clone();
+            let resume = config.new_blocks_only;
 
-            async move {
-                loop {
-                    rpc.listen_blocks(&db).await;
-
-                    sleep(Duration::from_millis(500)).await;
-                }
-            }
-        });
+            async move { rpc.listen_blocks(&db, resume).await }
+        }));
     }
 
-    // listend port for frontend
-    let t = HttpServer::new(|| {
-        App::new()
-            .wrap(Cors::permissive())
-            .wrap(middleware::Logger::default())
-            .configure(configure)
-    })
-    .bind(format!("{explorer_server_host}:{explorer_server_port}"))? // Bind server to localhost:8080
-    .run();
-    tokio::spawn(t);
-
     // sync chain
-    if !config.new_blocks_only {
+    if config.log_filter.is_some() {
+        sync_logs(&rpc, &db, &config).await;
+    } else if !config.new_blocks_only {
         sync_chain(&rpc, &db, &config).await;
+
+        // a finished or failed backfill stops the live pipeline of its chain too
+        shutdown.request();
+    }
+
+    if let Some(listener) = listener {
+        listener.await.ok();
     }
-    Ok(())
 }
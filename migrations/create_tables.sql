@@ -3,9 +3,12 @@ CREATE DATABASE IF NOT EXISTS satschain;
 CREATE TABLE satschain.blocks (
   base_block_reward UInt256,
   base_fee_per_gas Nullable(UInt64),
+  blob_gas_used Nullable(UInt64),
+  blob_transactions UInt16,
   burned UInt256,
   chain UInt64,
   difficulty UInt256,
+  excess_blob_gas Nullable(UInt64),
   extra_data String CODEC(ZSTD(9)),
   gas_limit UInt32,
   gas_used UInt32,
@@ -16,6 +19,7 @@ CREATE TABLE satschain.blocks (
   mix_hash Nullable(String),
   nonce String,
   number UInt32,
+  parent_beacon_block_root Nullable(String),
   parent_hash String,
   receipts_root String,
   sha3_uncles String,
@@ -28,6 +32,7 @@ CREATE TABLE satschain.blocks (
   transactions_root String,
   uncle_rewards UInt256,
   uncles Array(String),
+  withdrawals Nullable(UInt16),
   withdrawals_root Nullable(String)
 )
 ENGINE = ReplacingMergeTree()
@@ -129,6 +134,21 @@ PARTITION BY toYYYYMM(timestamp)
 ORDER BY (transaction_hash, address, chain, log_index, timestamp)
 SETTINGS index_granularity = 8192;
 
+CREATE TABLE satschain.decode_errors (
+  address String,
+  block_number UInt32,
+  chain UInt64,
+  error String,
+  log_index UInt16,
+  timestamp DateTime,
+  topic0 String,
+  transaction_hash String
+)
+ENGINE = ReplacingMergeTree()
+PARTITION BY toYYYYMM(timestamp)
+ORDER BY (transaction_hash, address, chain, log_index, timestamp)
+SETTINGS index_granularity = 8192;
+
 CREATE TABLE satschain.dex_trades (
   address String,
   block_number UInt32,
@@ -183,6 +203,9 @@ SETTINGS index_granularity = 8192;
 CREATE TABLE satschain.transactions (
   access_list Array(Tuple(String, Array(String))),
   base_fee_per_gas Nullable(UInt64),
+  blob_gas_price Nullable(UInt256),
+  blob_gas_used Nullable(UInt32),
+  blob_versioned_hashes Array(String),
   block_hash String,
   block_number UInt32,
   burned Nullable(UInt256),
@@ -195,17 +218,27 @@ CREATE TABLE satschain.transactions (
   gas UInt32,
   gas_price Nullable(UInt256),
   gas_used Nullable(UInt32),
+  gas_used_for_l1 Nullable(UInt32),
   hash String,
   input String CODEC(ZSTD(9)),
+  l1_blob_base_fee Nullable(UInt256),
+  l1_block_number Nullable(UInt64),
+  l1_fee Nullable(UInt256),
+  l1_gas_price Nullable(UInt256),
+  l1_gas_used Nullable(UInt64),
+  max_fee_per_blob_gas Nullable(UInt256),
   max_fee_per_gas Nullable(UInt256),
   max_priority_fee_per_gas Nullable(UInt256),
   method String,
+  mint Nullable(UInt256),
   nonce UInt32,
+  priority_fee Nullable(UInt256),
+  source_hash Nullable(String),
   status Nullable(Enum8('unknown' = 0, 'failure' = 1, 'success' = 2)),
   timestamp DateTime,
   to String,
   transaction_index UInt16,
-  transaction_type Enum8('legacy' = 0, 'access_list' = 1, 'eip_1559' = 2),
+  transaction_type Enum8('legacy' = 0, 'access_list' = 1, 'eip_1559' = 2, 'blob' = 3, 'set_code' = 4, 'arbitrum_deposit' = 100, 'arbitrum_unsigned' = 101, 'arbitrum_contract' = 102, 'arbitrum_retry' = 104, 'arbitrum_submit_retryable' = 105, 'arbitrum_internal' = 106, 'arbitrum_legacy' = 120, 'deposit' = 126),
   value UInt256
 )
 ENGINE = ReplacingMergeTree()
@@ -213,6 +246,40 @@ PARTITION BY toYYYYMM(timestamp)
 ORDER BY (hash, from, to, timestamp, chain, method)
 SETTINGS index_granularity = 8192;
 
+CREATE TABLE satschain.authorizations (
+  address String,
+  authority Nullable(String),
+  authorization_index UInt16,
+  block_number UInt32,
+  chain UInt64,
+  chain_id UInt256,
+  nonce UInt64,
+  timestamp DateTime,
+  transaction_hash String,
+  transaction_index UInt16
+)
+ENGINE = ReplacingMergeTree()
+PARTITION BY toYYYYMM(timestamp)
+ORDER BY (transaction_hash, authorization_index, chain, block_number)
+SETTINGS index_granularity = 8192;
+
+CREATE TABLE satschain.uncles (
+  block_hash String,
+  block_number UInt32,
+  chain UInt64,
+  hash String,
+  inclusion_distance UInt8,
+  miner String,
+  number UInt32,
+  reward UInt256,
+  timestamp DateTime,
+  uncle_index UInt8
+)
+ENGINE = ReplacingMergeTree()
+PARTITION BY toYYYYMM(timestamp)
+ORDER BY (block_number, chain, uncle_index)
+SETTINGS index_granularity = 8192;
+
 CREATE TABLE satschain.withdrawals (
   address String,
   amount UInt256,
@@ -220,7 +287,8 @@ CREATE TABLE satschain.withdrawals (
   chain UInt64,
   timestamp DateTime,
   validator_index UInt32,
-  withdrawal_index UInt32
+  withdrawal_index UInt32,
+  withdrawal_type Enum8('partial' = 0, 'full' = 1)
 )
 ENGINE = ReplacingMergeTree()
 PARTITION BY toYYYYMM(timestamp)
@@ -228,11 +296,13 @@ ORDER BY (address, block_number, chain, timestamp, validator_index)
 SETTINGS index_granularity = 8192;
 
 CREATE TABLE satschain.infoforsync (
+  chain UInt64,
   end_block UInt32,
+  job String DEFAULT 'tip',
   missing_blocks Array(UInt32),
   timestamp DateTime,
 )
 ENGINE = ReplacingMergeTree()
 PARTITION BY toYYYYMM(timestamp)
-ORDER BY (timestamp)
+ORDER BY (chain, job, timestamp)
 SETTINGS index_granularity = 8192;
\ No newline at end of file
@@ -1,191 +1,299 @@
-use std::{
-    collections::HashMap,
-    ops::{AddAssign, DivAssign, Mul, MulAssign, SubAssign},
-    str::FromStr,
-};
+pub mod rewards;
+
+use std::{collections::HashMap, fs, path::Path, sync::Arc};
 
-use ethers::types::TransactionReceipt;
 use primitive_types::U256;
 use serde::{Deserialize, Serialize};
 
-use crate::db::models::block::DatabaseBlock;
+use self::rewards::{
+    BlockRewardModel, FeesOnly, ProofOfWork, RewardEpoch,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct BalanceAllocation {
     balance: U256,
 }
 
-#[derive(Debug, Clone)]
+// How the block producer is paid on a chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RewardModel {
+    // Ethereum issuance with its proof of work fork schedule, plus the transaction fees.
+    Ethereum,
+    // Only the transaction fees, e.g. proof of stake or proof of authority chains.
+    FeesOnly,
+    // Proof of work issuance following the `reward_schedule` of the chain, plus the fees.
+    Custom,
+}
+
+// Rollup stack of a layer 2 chain, deciding which extra receipt fields are indexed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rollup {
+    // Optimism, Base and the other OP stack chains, which charge an L1 data fee on top of the gas.
+    OpStack,
+    // Arbitrum chains, whose gas used already includes the gas charged for the L1 data.
+    Arbitrum,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Chain {
-    pub genesis_hash: &'static str,
+    // Geth genesis file whose `alloc` section is indexed, instead of the built-in allocations.
+    #[serde(default)]
+    pub genesis_file: Option<String>,
+    pub genesis_hash: String,
     pub genesis_timestamp: u32,
+    // Milliseconds to wait after a new head before fetching it, so nodes can execute the block.
+    #[serde(default)]
+    pub head_delay: u64,
     pub id: u64,
-    pub name: &'static str,
+    pub name: String,
+    #[serde(default = "default_native_decimals")]
+    pub native_decimals: u8,
+    pub native_symbol: String,
+    pub reward_model: RewardModel,
+    #[serde(default)]
+    pub reward_schedule: Vec<RewardEpoch>,
+    #[serde(default)]
+    pub rollup: Option<Rollup>,
+    #[serde(default)]
     pub supports_blocks_receipts: bool,
+    #[serde(default)]
     pub supports_trace_block: bool,
+    #[serde(default)]
     pub has_miner_rewards: bool,
+    // Wrapped version of the native token, e.g. WETH.
+    #[serde(default)]
+    pub wrapped_token: Option<String>,
 }
 
-pub const ETHEREUM: Chain = Chain {
-    genesis_hash: "0xd4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3",
-    genesis_timestamp: 1438249573,
-    id: 1,
-    name: "ethereum",
-    supports_blocks_receipts: true,
-    supports_trace_block: true,
-    has_miner_rewards: true,
-};
-
-fn calculate_ethereum_block_reward(
-    block: &DatabaseBlock,
-    receipts: Option<&HashMap<String, TransactionReceipt>>,
-    uncles: &[DatabaseBlock],
-    is_uncle: bool,
-    uncle_parent_number: Option<u32>,
-) -> (U256, U256, U256) {
-    // The ETH base reward is 5 ETH
-    let mut base_block_reward =
-        U256::from_str("0x4563918244f40000").unwrap();
-
-    // If the block is above the Bizantium fork the reward is 3 ETH
-    if block.number > 4_370_000 {
-        base_block_reward = U256::from_str("0x29a2241af62c0000").unwrap();
-    }
-
-    // If the block is above the Constantinople fork the reward is 3 ETH
-    if block.number > 7_280_000 {
-        base_block_reward = U256::from_str("0x1bc16d674ec80000").unwrap();
+impl Chain {
+    pub fn block_reward_model(&self) -> Arc<dyn BlockRewardModel> {
+        match self.reward_model {
+            RewardModel::Ethereum => Arc::new(ProofOfWork::ethereum()),
+            RewardModel::FeesOnly => Arc::new(FeesOnly),
+            RewardModel::Custom => {
+                Arc::new(ProofOfWork::new(self.reward_schedule.clone()))
+            }
+        }
     }
+}
 
-    // If the block is above The Merge the base block reward is 0 ETH
-    if block.number > 15_537_393 {
-        base_block_reward = U256::zero();
-    }
+fn default_native_decimals() -> u8 {
+    18
+}
 
-    let mut uncle_rewards = U256::zero();
+// Indexing settings of a chain indexed with `--chains`, overriding the command line values.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChainSync {
+    #[serde(default)]
+    pub batch_size: Option<usize>,
+    pub chain: u64,
+    #[serde(default)]
+    pub rpcs: Vec<String>,
+    #[serde(default)]
+    pub start_block: Option<u32>,
+    #[serde(default)]
+    pub ws: Option<String>,
+}
 
-    if !uncles.is_empty() {
-        let base_uncle_reward =
-            U256::from_str("0xde0b6b3a764000").unwrap();
+// Chains and indexing settings declared in a chains file, e.g. `[[chains]]` and `[[sync]]`
+// tables of a TOML file.
+#[derive(Debug, Deserialize)]
+struct ChainsFile {
+    #[serde(default)]
+    chains: Vec<Chain>,
+    #[serde(default)]
+    sync: Vec<ChainSync>,
+}
 
-        uncle_rewards = base_uncle_reward.mul(uncles.len());
+pub fn ethereum() -> Chain {
+    Chain {
+        genesis_file: None,
+        genesis_hash: String::from("0xd4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3"),
+        genesis_timestamp: 1438249573,
+        head_delay: 300,
+        id: 1,
+        name: String::from("ethereum"),
+        native_decimals: 18,
+        native_symbol: String::from("ETH"),
+        reward_model: RewardModel::Ethereum,
+        reward_schedule: Vec::new(),
+        rollup: None,
+        supports_blocks_receipts: true,
+        supports_trace_block: true,
+        has_miner_rewards: true,
+        wrapped_token: Some(String::from(
+            "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+        )),
     }
+}
 
-    if is_uncle {
-        let mut uncle_block_reward = U256::zero();
-
-        uncle_block_reward.add_assign(U256::from(8));
-        uncle_block_reward.add_assign(U256::from(block.number));
-        uncle_block_reward
-            .sub_assign(U256::from(uncle_parent_number.unwrap()));
+pub fn polygon() -> Chain {
+    Chain {
+        genesis_file: None,
+        genesis_hash: String::from("0xa9c28ce2141b56c474f1dc504bee9b01eb1bd7d1a507580d5519d4437a97de1b"),
+        genesis_timestamp: 1590814036,
+        head_delay: 0,
+        id: 137,
+        name: String::from("polygon"),
+        native_decimals: 18,
+        native_symbol: String::from("MATIC"),
+        reward_model: RewardModel::FeesOnly,
+        reward_schedule: Vec::new(),
+        rollup: None,
+        supports_blocks_receipts: true,
+        supports_trace_block: true,
+        has_miner_rewards: true,
+        wrapped_token: Some(String::from(
+            "0x0d500b1d8e8ef31e21c99d1db9a6444d3adf1270",
+        )),
+    }
+}
 
-        uncle_block_reward.mul_assign(base_block_reward);
+pub fn bsc() -> Chain {
+    Chain {
+        genesis_file: None,
+        genesis_hash: String::from("0x0d21840abff46b96c84b2ac9e10e4f5cdaeb5693cb665db62a2f3b02d2d57b5b"),
+        genesis_timestamp: 1598687048,
+        head_delay: 4000,
+        id: 56,
+        name: String::from("bsc"),
+        native_decimals: 18,
+        native_symbol: String::from("BNB"),
+        reward_model: RewardModel::FeesOnly,
+        reward_schedule: Vec::new(),
+        rollup: None,
+        supports_blocks_receipts: true,
+        supports_trace_block: true,
+        has_miner_rewards: true,
+        wrapped_token: Some(String::from(
+            "0xbb4cdb9cbd36b01bd1cbaebf2de08d9173bc095c",
+        )),
+    }
+}
 
-        uncle_block_reward.div_assign(U256::from(8));
+pub fn optimism() -> Chain {
+    Chain {
+        genesis_file: None,
+        genesis_hash: String::from("0x7ca38a1916c42007829c55e69d3e9a73265554b586a499015373241b8a3fa48b"),
+        genesis_timestamp: 1610639500,
+        head_delay: 0,
+        id: 10,
+        name: String::from("optimism"),
+        native_decimals: 18,
+        native_symbol: String::from("ETH"),
+        reward_model: RewardModel::FeesOnly,
+        reward_schedule: Vec::new(),
+        rollup: Some(Rollup::OpStack),
+        supports_blocks_receipts: true,
+        supports_trace_block: false,
+        has_miner_rewards: false,
+        wrapped_token: Some(String::from(
+            "0x4200000000000000000000000000000000000006",
+        )),
+    }
+}
 
-        return (uncle_block_reward, U256::zero(), U256::zero());
+pub fn base() -> Chain {
+    Chain {
+        genesis_file: None,
+        genesis_hash: String::from("0xf712aa9241cc24369b143cf6dce85f0902a9731e70d66818a3a5845b296c73dd"),
+        genesis_timestamp: 1686789347,
+        head_delay: 0,
+        id: 8453,
+        name: String::from("base"),
+        native_decimals: 18,
+        native_symbol: String::from("ETH"),
+        reward_model: RewardModel::FeesOnly,
+        reward_schedule: Vec::new(),
+        rollup: Some(Rollup::OpStack),
+        supports_blocks_receipts: true,
+        supports_trace_block: false,
+        has_miner_rewards: false,
+        wrapped_token: Some(String::from(
+            "0x4200000000000000000000000000000000000006",
+        )),
     }
+}
 
-    (base_block_reward, get_total_fees(receipts), uncle_rewards)
+pub fn arbitrum() -> Chain {
+    Chain {
+        genesis_file: None,
+        genesis_hash: String::from("0x7ee576b35482195fc49205cec9af72ce14f003b9ae69f6ba0faef4514be8b442"),
+        genesis_timestamp: 1622240000,
+        head_delay: 0,
+        id: 42161,
+        name: String::from("arbitrum"),
+        native_decimals: 18,
+        native_symbol: String::from("ETH"),
+        reward_model: RewardModel::FeesOnly,
+        reward_schedule: Vec::new(),
+        rollup: Some(Rollup::Arbitrum),
+        supports_blocks_receipts: true,
+        supports_trace_block: false,
+        has_miner_rewards: false,
+        wrapped_token: Some(String::from(
+            "0x82af49447d8a07e3bd95bd0d56f35241523fbab1",
+        )),
+    }
 }
 
-pub const POLYGON: Chain = Chain {
-    genesis_hash: "0xa9c28ce2141b56c474f1dc504bee9b01eb1bd7d1a507580d5519d4437a97de1b",
-    genesis_timestamp: 1590814036,
-    id: 137,
-    name: "polygon",
-    supports_blocks_receipts: true,
-    supports_trace_block: true,
-    has_miner_rewards: true,
-};
+// Reads a chains file in TOML or, with a `.json` extension, JSON.
+fn read_chains_file(path: &str) -> Result<ChainsFile, String> {
+    let content = fs::read_to_string(path).map_err(|err| {
+        format!("unable to read chains file {}: {}", path, err)
+    })?;
+
+    let chains_file: ChainsFile =
+        if Path::new(path).extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&content).map_err(|err| err.to_string())
+        } else {
+            toml::from_str(&content).map_err(|err| err.to_string())
+        }
+        .map_err(|err| {
+            format!("unable to parse chains file {}: {}", path, err)
+        })?;
 
-fn calculate_polygon_block_reward(
-    receipts: Option<&HashMap<String, TransactionReceipt>>,
-) -> (U256, U256, U256) {
-    (
-        U256::from_dec_str("0").unwrap(),
-        get_total_fees(receipts),
-        U256::from_dec_str("0").unwrap(),
-    )
-}
-
-pub const BSC: Chain = Chain {
-    genesis_hash: "0x0d21840abff46b96c84b2ac9e10e4f5cdaeb5693cb665db62a2f3b02d2d57b5b",
-    genesis_timestamp: 1598687048,
-    id: 56,
-    name: "bsc",
-    supports_blocks_receipts: true,
-    supports_trace_block: true,
-    has_miner_rewards: true,
-};
+    Ok(chains_file)
+}
 
-fn calculate_bsc_block_reward(
-    receipts: Option<&HashMap<String, TransactionReceipt>>,
-) -> (U256, U256, U256) {
-    (
-        U256::from_dec_str("0").unwrap(),
-        get_total_fees(receipts),
-        U256::from_dec_str("0").unwrap(),
-    )
+pub fn load_chains(path: &str) -> Result<Vec<Chain>, String> {
+    Ok(read_chains_file(path)?.chains)
 }
 
-pub static CHAINS: [Chain; 3] = [ETHEREUM, POLYGON, BSC];
+pub fn load_chain_syncs(path: &str) -> Result<Vec<ChainSync>, String> {
+    Ok(read_chains_file(path)?.sync)
+}
 
-pub fn get_chains() -> HashMap<u64, Chain> {
+// Built-in chains, replaced or extended by the ones of the chains file.
+pub fn get_chains(chains_file: Option<&str>) -> HashMap<u64, Chain> {
     let mut chains: HashMap<u64, Chain> = HashMap::new();
 
-    for chain in CHAINS.iter() {
-        chains.insert(chain.id, chain.to_owned());
+    for chain in
+        [ethereum(), polygon(), bsc(), optimism(), base(), arbitrum()]
+    {
+        chains.insert(chain.id, chain);
     }
 
-    chains
-}
-
-pub fn get_chain(chain: u64) -> Chain {
-    let chains = get_chains();
-
-    let selected_chain = chains.get(&chain).expect("chain not found.");
+    if let Some(path) = chains_file {
+        for chain in
+            load_chains(path).unwrap_or_else(|err| panic!("{}", err))
+        {
+            chains.insert(chain.id, chain);
+        }
+    }
 
-    selected_chain.to_owned()
+    chains
 }
 
-fn get_total_fees(
-    receipts: Option<&HashMap<String, TransactionReceipt>>,
-) -> U256 {
-    let mut fees_reward = U256::zero();
+pub fn get_chain(chain: u64, chains_file: Option<&str>) -> Chain {
+    let mut chains = get_chains(chains_file);
 
-    if let Some(receipts) = receipts {
-        for receipt in receipts.values() {
-            let reward = receipt
-                .gas_used
-                .unwrap()
-                .mul(receipt.effective_gas_price.unwrap());
-
-            fees_reward.add_assign(reward);
-        }
-    }
-
-    fees_reward
-}
-
-pub fn get_block_reward(
-    chain: u64,
-    block: &DatabaseBlock,
-    receipts: Option<&HashMap<String, TransactionReceipt>>,
-    uncles: &[DatabaseBlock],
-    is_uncle: bool,
-    uncle_parent_number: Option<u32>,
-) -> (U256, U256, U256) {
-    match chain {
-        1 => calculate_ethereum_block_reward(
-            block,
-            receipts,
-            uncles,
-            is_uncle,
-            uncle_parent_number,
-        ),
-        56 => calculate_bsc_block_reward(receipts),
-        137 => calculate_polygon_block_reward(receipts),
-        _ => panic!("invalid chain"),
-    }
+    chains.remove(&chain).unwrap_or_else(|| {
+        panic!(
+            "chain {} not found, declare it in a chains file with --chains-file",
+            chain
+        )
+    })
 }
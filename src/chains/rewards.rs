@@ -0,0 +1,407 @@
+use std::{
+    collections::HashMap,
+    ops::{AddAssign, Mul},
+};
+
+use ethers::types::TransactionReceipt;
+use primitive_types::U256;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    db::models::{
+        block::DatabaseBlock,
+        trace::{DatabaseTrace, TraceType},
+    },
+    utils::format::deserialize_u256_str,
+};
+
+// Ethereum forks changing the base block reward (EIP-649, EIP-1234 and The Merge).
+pub const ETHEREUM_BYZANTIUM_BLOCK: u32 = 4_370_000;
+pub const ETHEREUM_CONSTANTINOPLE_BLOCK: u32 = 7_280_000;
+pub const ETHEREUM_MERGE_BLOCK: u32 = 15_537_394;
+
+// Uncles older than this amount of blocks can't be included anymore.
+const MAX_UNCLE_DEPTH: u32 = 8;
+
+// Share of the base reward paid to the producer for each included uncle.
+const UNCLE_INCLUSION_DIVISOR: u64 = 32;
+
+// Rewards paid to the producer of a block.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BlockReward {
+    // Issuance for producing the block.
+    pub base_reward: U256,
+    // Transaction fees paid to the producer.
+    pub fees: U256,
+    // Issuance for including uncles.
+    pub uncles_reward: U256,
+}
+
+// Base reward paid from `from_block` until the next epoch of a schedule.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RewardEpoch {
+    pub from_block: u32,
+    #[serde(deserialize_with = "deserialize_u256_str")]
+    pub reward: U256,
+}
+
+pub trait BlockRewardModel: std::fmt::Debug + Send + Sync {
+    fn block_reward(
+        &self,
+        block: &DatabaseBlock,
+        receipts: Option<&HashMap<String, TransactionReceipt>>,
+        uncles: &[DatabaseBlock],
+    ) -> BlockReward;
+
+    // Reward of the producer of `uncle`, included by the block `nephew_number`.
+    fn uncle_reward(
+        &self,
+        uncle: &DatabaseBlock,
+        nephew_number: u32,
+    ) -> U256;
+}
+
+// Producers only earn the transaction fees, e.g. on proof of stake or proof of authority chains.
+#[derive(Debug, Clone)]
+pub struct FeesOnly;
+
+impl BlockRewardModel for FeesOnly {
+    fn block_reward(
+        &self,
+        block: &DatabaseBlock,
+        receipts: Option<&HashMap<String, TransactionReceipt>>,
+        _uncles: &[DatabaseBlock],
+    ) -> BlockReward {
+        BlockReward {
+            fees: get_total_fees(receipts, block.base_fee_per_gas),
+            ..Default::default()
+        }
+    }
+
+    fn uncle_reward(
+        &self,
+        _uncle: &DatabaseBlock,
+        _nephew_number: u32,
+    ) -> U256 {
+        U256::zero()
+    }
+}
+
+// Proof of work issuance following a fork schedule, with the Ethash uncle rewards, plus the
+// transaction fees.
+#[derive(Debug, Clone)]
+pub struct ProofOfWork {
+    schedule: Vec<RewardEpoch>,
+}
+
+impl ProofOfWork {
+    pub fn new(mut schedule: Vec<RewardEpoch>) -> Self {
+        schedule.sort_by_key(|epoch| epoch.from_block);
+
+        Self { schedule }
+    }
+
+    pub fn ethereum() -> Self {
+        let ether = U256::exp10(18);
+
+        Self::new(vec![
+            RewardEpoch { from_block: 0, reward: ether.mul(5) },
+            RewardEpoch {
+                from_block: ETHEREUM_BYZANTIUM_BLOCK,
+                reward: ether.mul(3),
+            },
+            RewardEpoch {
+                from_block: ETHEREUM_CONSTANTINOPLE_BLOCK,
+                reward: ether.mul(2),
+            },
+            RewardEpoch {
+                from_block: ETHEREUM_MERGE_BLOCK,
+                reward: U256::zero(),
+            },
+        ])
+    }
+
+    fn base_reward(&self, block_number: u32) -> U256 {
+        self.schedule
+            .iter()
+            .rev()
+            .find(|epoch| epoch.from_block <= block_number)
+            .map_or(U256::zero(), |epoch| epoch.reward)
+    }
+}
+
+impl BlockRewardModel for ProofOfWork {
+    fn block_reward(
+        &self,
+        block: &DatabaseBlock,
+        receipts: Option<&HashMap<String, TransactionReceipt>>,
+        uncles: &[DatabaseBlock],
+    ) -> BlockReward {
+        let base_reward = self.base_reward(block.number);
+
+        BlockReward {
+            base_reward,
+            fees: get_total_fees(receipts, block.base_fee_per_gas),
+            uncles_reward: (base_reward / UNCLE_INCLUSION_DIVISOR)
+                .mul(uncles.len()),
+        }
+    }
+
+    fn uncle_reward(
+        &self,
+        uncle: &DatabaseBlock,
+        nephew_number: u32,
+    ) -> U256 {
+        let depth = nephew_number.saturating_sub(uncle.number);
+
+        if depth == 0 || depth >= MAX_UNCLE_DEPTH {
+            return U256::zero();
+        }
+
+        // The uncle reward is paid at the rate of the block including it.
+        self.base_reward(nephew_number).mul(MAX_UNCLE_DEPTH - depth)
+            / MAX_UNCLE_DEPTH
+    }
+}
+
+// Part of the fee of a transaction paid to the block producer. Since London the base fee is
+// burned and only the tip above it is earned, before it the whole fee is.
+pub fn get_priority_fee(
+    receipt: &TransactionReceipt,
+    base_fee_per_gas: Option<u64>,
+) -> U256 {
+    // Receipts missing any of the values (e.g. from a misbehaving node) add no fees.
+    let tip_per_gas = receipt
+        .effective_gas_price
+        .unwrap_or_default()
+        .saturating_sub(U256::from(base_fee_per_gas.unwrap_or_default()));
+
+    receipt.gas_used.unwrap_or_default().mul(tip_per_gas)
+}
+
+// Blob gas price and blob gas used of the receipt of a blob transaction, ethers leaves them in the
+// other fields.
+pub fn get_receipt_blob_gas(
+    receipt: &TransactionReceipt,
+) -> Option<(U256, U256)> {
+    let blob_gas_price: U256 =
+        receipt.other.get_deserialized("blobGasPrice")?.ok()?;
+    let blob_gas_used: U256 =
+        receipt.other.get_deserialized("blobGasUsed")?.ok()?;
+
+    Some((blob_gas_price, blob_gas_used))
+}
+
+// Fee paid for the blobs of a transaction, it is burned in full.
+pub fn get_blob_fee(receipt: &TransactionReceipt) -> U256 {
+    match get_receipt_blob_gas(receipt) {
+        Some((blob_gas_price, blob_gas_used)) => {
+            blob_gas_price.mul(blob_gas_used)
+        }
+        None => U256::zero(),
+    }
+}
+
+// Fees earned by the producer of a block, the burned base fees are left out.
+pub fn get_total_fees(
+    receipts: Option<&HashMap<String, TransactionReceipt>>,
+    base_fee_per_gas: Option<u64>,
+) -> U256 {
+    let mut fees_reward = U256::zero();
+
+    if let Some(receipts) = receipts {
+        for receipt in receipts.values() {
+            fees_reward
+                .add_assign(get_priority_fee(receipt, base_fee_per_gas));
+        }
+    }
+
+    fees_reward
+}
+
+// Compares the issuance computed for a block and its uncles with the reward traces of the block,
+// when the node provides them.
+pub fn check_reward_traces(
+    block: &DatabaseBlock,
+    uncles: &[DatabaseBlock],
+    traces: &[DatabaseTrace],
+) -> Result<(), String> {
+    let mut traced: Vec<(String, U256)> = traces
+        .iter()
+        .filter(|trace| trace.action_type == TraceType::Reward)
+        .map(|trace| {
+            (
+                trace.author.clone().unwrap_or_default(),
+                trace.value.unwrap_or_default(),
+            )
+        })
+        .collect();
+
+    if traced.is_empty() {
+        return Ok(());
+    }
+
+    let mut computed: Vec<(String, U256)> = vec![(
+        block.miner.clone(),
+        block.base_block_reward + block.uncle_rewards,
+    )];
+    computed.extend(
+        uncles
+            .iter()
+            .map(|uncle| (uncle.miner.clone(), uncle.base_block_reward)),
+    );
+
+    traced.sort();
+    computed.sort();
+
+    if traced != computed {
+        return Err(format!(
+            "rewards of block {} {:?} don't match its reward traces {:?}",
+            block.number, computed, traced
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GWEI: u64 = 1_000_000_000;
+
+    fn block(number: u32) -> DatabaseBlock {
+        let mut block = DatabaseBlock::new();
+        block.number = number;
+        block
+    }
+
+    fn ether(amount: u64) -> U256 {
+        U256::exp10(18).mul(amount)
+    }
+
+    fn receipt(
+        gas_used: u64,
+        effective_gas_price: u64,
+    ) -> TransactionReceipt {
+        TransactionReceipt {
+            effective_gas_price: Some(U256::from(effective_gas_price)),
+            gas_used: Some(U256::from(gas_used)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn base_reward_follows_the_forks() {
+        let model = ProofOfWork::ethereum();
+
+        for (number, reward) in [
+            (0, ether(5)),
+            (ETHEREUM_BYZANTIUM_BLOCK - 1, ether(5)),
+            (ETHEREUM_BYZANTIUM_BLOCK, ether(3)),
+            (ETHEREUM_CONSTANTINOPLE_BLOCK, ether(2)),
+            (ETHEREUM_MERGE_BLOCK - 1, ether(2)),
+            (ETHEREUM_MERGE_BLOCK, U256::zero()),
+        ] {
+            assert_eq!(
+                model.block_reward(&block(number), None, &[]).base_reward,
+                reward
+            );
+        }
+    }
+
+    #[test]
+    fn nephew_earns_a_share_per_uncle() {
+        let model = ProofOfWork::ethereum();
+        let nephew = block(ETHEREUM_CONSTANTINOPLE_BLOCK + 100);
+        let uncles = [block(nephew.number - 1), block(nephew.number - 2)];
+
+        assert_eq!(
+            model.block_reward(&nephew, None, &uncles).uncles_reward,
+            ether(2) / UNCLE_INCLUSION_DIVISOR * 2
+        );
+    }
+
+    #[test]
+    fn uncle_reward_decreases_with_the_depth() {
+        let model = ProofOfWork::ethereum();
+        let nephew_number = ETHEREUM_CONSTANTINOPLE_BLOCK + 100;
+
+        for depth in 1..MAX_UNCLE_DEPTH {
+            assert_eq!(
+                model.uncle_reward(
+                    &block(nephew_number - depth),
+                    nephew_number
+                ),
+                ether(2) * (MAX_UNCLE_DEPTH - depth) / MAX_UNCLE_DEPTH
+            );
+        }
+    }
+
+    #[test]
+    fn uncle_reward_is_zero_out_of_depth() {
+        let model = ProofOfWork::ethereum();
+        let nephew_number = ETHEREUM_CONSTANTINOPLE_BLOCK + 100;
+
+        for uncle_number in [
+            nephew_number + 1,
+            nephew_number,
+            nephew_number - MAX_UNCLE_DEPTH,
+        ] {
+            assert_eq!(
+                model.uncle_reward(&block(uncle_number), nephew_number),
+                U256::zero()
+            );
+        }
+    }
+
+    #[test]
+    fn uncle_reward_is_paid_at_the_rate_of_the_nephew() {
+        let model = ProofOfWork::ethereum();
+
+        assert_eq!(
+            model.uncle_reward(
+                &block(ETHEREUM_BYZANTIUM_BLOCK - 1),
+                ETHEREUM_BYZANTIUM_BLOCK
+            ),
+            ether(3) * 7 / 8
+        );
+    }
+
+    #[test]
+    fn fees_only_pays_no_uncle_reward() {
+        assert_eq!(FeesOnly.uncle_reward(&block(99), 100), U256::zero());
+    }
+
+    #[test]
+    fn priority_fee_leaves_out_the_base_fee() {
+        assert_eq!(
+            get_priority_fee(&receipt(21_000, 30 * GWEI), Some(25 * GWEI)),
+            U256::from(21_000 * 5 * GWEI)
+        );
+    }
+
+    #[test]
+    fn priority_fee_is_the_whole_fee_before_london() {
+        assert_eq!(
+            get_priority_fee(&receipt(21_000, 30 * GWEI), None),
+            U256::from(21_000 * 30 * GWEI)
+        );
+    }
+
+    #[test]
+    fn priority_fee_is_zero_below_the_base_fee() {
+        assert_eq!(
+            get_priority_fee(&receipt(21_000, 20 * GWEI), Some(25 * GWEI)),
+            U256::zero()
+        );
+    }
+
+    #[test]
+    fn priority_fee_is_zero_for_incomplete_receipts() {
+        assert_eq!(
+            get_priority_fee(&TransactionReceipt::default(), Some(GWEI)),
+            U256::zero()
+        );
+    }
+}
@@ -1,73 +1,240 @@
 pub mod models;
 
 use self::models::{
-    dex_trade::DatabaseDexTrade,
+    decode_error::DatabaseDecodeError, dex_trade::DatabaseDexTrade,
     erc1155_transfer::DatabaseERC1155Transfer,
     erc20_transfer::DatabaseERC20Transfer,
     erc721_transfer::DatabaseERC721Transfer,
 };
 use crate::{
     chains::Chain,
+    configs::Dataset,
+    errors::IndexerError,
     explorer::models::{ChartTransactionResponse, InfoForAverageBlock},
 };
-use clickhouse::{Client, Row};
+use chrono::Utc;
+use clickhouse::{sql::Bind, Client, Row};
 use futures::future::join_all;
-use log::{error, info};
+use log::{error, info, warn};
 use models::{
-    block::DatabaseBlock, contract::DatabaseContract,
-    infoforsync::DatabaseInfoForSync, log::DatabaseLog,
-    trace::DatabaseTrace, transaction::DatabaseTransaction,
+    authorization::DatabaseAuthorization,
+    block::DatabaseBlock,
+    block_audit::DatabaseBlockAudit,
+    contract::DatabaseContract,
+    infoforsync::{is_logs_sync_job, sync_job_range, DatabaseInfoForSync},
+    log::DatabaseLog,
+    trace::DatabaseTrace,
+    transaction::DatabaseTransaction,
+    uncle::DatabaseUncle,
     withdrawal::DatabaseWithdrawal,
+    withdrawal_summary::DatabaseWithdrawalSummary,
 };
-use serde::Serialize;
-use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, mem::size_of, sync::Arc, time::Duration};
+use tokio::{sync::Mutex, time::sleep};
 
+// Attempts to store a table before giving up, and bounds of the backoff between them.
+const STORE_ATTEMPTS: usize = 5;
+const STORE_RETRY_MIN_BACKOFF: Duration = Duration::from_millis(500);
+const STORE_RETRY_MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone)]
 pub struct BlockFetchedData {
+    pub authorizations: Vec<DatabaseAuthorization>,
     pub blocks: Vec<DatabaseBlock>,
     pub contracts: Vec<DatabaseContract>,
     pub logs: Vec<DatabaseLog>,
     pub traces: Vec<DatabaseTrace>,
     pub transactions: Vec<DatabaseTransaction>,
+    pub uncles: Vec<DatabaseUncle>,
     pub withdrawals: Vec<DatabaseWithdrawal>,
     pub erc20_transfers: Vec<DatabaseERC20Transfer>,
     pub erc721_transfers: Vec<DatabaseERC721Transfer>,
     pub erc1155_transfers: Vec<DatabaseERC1155Transfer>,
     pub dex_trades: Vec<DatabaseDexTrade>,
+    pub decode_errors: Vec<DatabaseDecodeError>,
+}
+
+impl Default for BlockFetchedData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BlockFetchedData {
+    pub fn new() -> Self {
+        Self {
+            authorizations: Vec::new(),
+            blocks: Vec::new(),
+            contracts: Vec::new(),
+            logs: Vec::new(),
+            traces: Vec::new(),
+            transactions: Vec::new(),
+            uncles: Vec::new(),
+            withdrawals: Vec::new(),
+            erc20_transfers: Vec::new(),
+            erc721_transfers: Vec::new(),
+            erc1155_transfers: Vec::new(),
+            dex_trades: Vec::new(),
+            decode_errors: Vec::new(),
+        }
+    }
+
+    pub fn append(&mut self, other: &mut BlockFetchedData) {
+        self.authorizations.append(&mut other.authorizations);
+        self.blocks.append(&mut other.blocks);
+        self.contracts.append(&mut other.contracts);
+        self.logs.append(&mut other.logs);
+        self.traces.append(&mut other.traces);
+        self.transactions.append(&mut other.transactions);
+        self.uncles.append(&mut other.uncles);
+        self.withdrawals.append(&mut other.withdrawals);
+        self.erc20_transfers.append(&mut other.erc20_transfers);
+        self.erc721_transfers.append(&mut other.erc721_transfers);
+        self.erc1155_transfers.append(&mut other.erc1155_transfers);
+        self.dex_trades.append(&mut other.dex_trades);
+        self.decode_errors.append(&mut other.decode_errors);
+    }
+
+    pub fn rows(&self) -> usize {
+        self.authorizations.len()
+            + self.blocks.len()
+            + self.contracts.len()
+            + self.logs.len()
+            + self.traces.len()
+            + self.transactions.len()
+            + self.uncles.len()
+            + self.withdrawals.len()
+            + self.erc20_transfers.len()
+            + self.erc721_transfers.len()
+            + self.erc1155_transfers.len()
+            + self.dex_trades.len()
+            + self.decode_errors.len()
+    }
+
+    // Rough amount of bytes held by the fetched rows, used to bound the data waiting to be stored.
+    pub fn estimated_size(&self) -> usize {
+        let rows_size = self.authorizations.len()
+            * size_of::<DatabaseAuthorization>()
+            + self.blocks.len() * size_of::<DatabaseBlock>()
+            + self.contracts.len() * size_of::<DatabaseContract>()
+            + self.logs.len() * size_of::<DatabaseLog>()
+            + self.traces.len() * size_of::<DatabaseTrace>()
+            + self.transactions.len() * size_of::<DatabaseTransaction>()
+            + self.uncles.len() * size_of::<DatabaseUncle>()
+            + self.withdrawals.len() * size_of::<DatabaseWithdrawal>()
+            + self.erc20_transfers.len()
+                * size_of::<DatabaseERC20Transfer>()
+            + self.erc721_transfers.len()
+                * size_of::<DatabaseERC721Transfer>()
+            + self.erc1155_transfers.len()
+                * size_of::<DatabaseERC1155Transfer>()
+            + self.dex_trades.len() * size_of::<DatabaseDexTrade>()
+            + self.decode_errors.len() * size_of::<DatabaseDecodeError>();
+
+        // Variable length payloads dominate the size of heavy blocks.
+        let payloads_size = self
+            .blocks
+            .iter()
+            .map(|block| block.extra_data.len() + block.logs_bloom.len())
+            .sum::<usize>()
+            + self
+                .transactions
+                .iter()
+                .map(|transaction| transaction.input.len())
+                .sum::<usize>()
+            + self.logs.iter().map(|log| log.data.len()).sum::<usize>()
+            + self
+                .traces
+                .iter()
+                .map(|trace| {
+                    [&trace.input, &trace.output, &trace.init, &trace.code]
+                        .iter()
+                        .map(|payload| {
+                            payload.as_ref().map_or(0, String::len)
+                        })
+                        .sum::<usize>()
+                })
+                .sum::<usize>();
+
+        rows_size + payloads_size
+    }
 }
 
 #[derive(Clone)]
 pub struct Database {
     pub chain: Chain,
+    // Datasets stored by `store_data`, the rows of the others are dropped.
+    pub datasets: HashSet<Dataset>,
     pub db: Client,
+    // Serializes checkpoint updates from the backfill and the live pipeline.
+    info_for_sync_lock: Arc<Mutex<()>>,
 }
 
 pub enum DatabaseTables {
+    Authorizations,
     Blocks,
     Contracts,
     Logs,
     Traces,
     Transactions,
+    Uncles,
     Withdrawals,
     Erc20Transfers,
     Erc721Transfers,
     Erc1155Transfers,
     DexTrades,
+    DecodeErrors,
     InfoForSync,
 }
 
 impl DatabaseTables {
+    // Tables whose rows belong to a block and are stored before it.
+    pub fn children() -> [DatabaseTables; 12] {
+        [
+            DatabaseTables::Authorizations,
+            DatabaseTables::Contracts,
+            DatabaseTables::Logs,
+            DatabaseTables::Traces,
+            DatabaseTables::Transactions,
+            DatabaseTables::Uncles,
+            DatabaseTables::Withdrawals,
+            DatabaseTables::Erc20Transfers,
+            DatabaseTables::Erc721Transfers,
+            DatabaseTables::Erc1155Transfers,
+            DatabaseTables::DexTrades,
+            DatabaseTables::DecodeErrors,
+        ]
+    }
+
+    // Tables the logs mode writes, without the blocks of their rows.
+    pub fn is_written_by_logs(&self) -> bool {
+        matches!(
+            self,
+            DatabaseTables::Logs
+                | DatabaseTables::Erc20Transfers
+                | DatabaseTables::Erc721Transfers
+                | DatabaseTables::Erc1155Transfers
+                | DatabaseTables::DexTrades
+                | DatabaseTables::DecodeErrors
+        )
+    }
+
     pub fn as_str(&self) -> &'static str {
         match self {
+            DatabaseTables::Authorizations => "authorizations",
             DatabaseTables::Blocks => "blocks",
             DatabaseTables::Contracts => "contracts",
             DatabaseTables::Logs => "logs",
             DatabaseTables::Traces => "traces",
             DatabaseTables::Transactions => "transactions",
+            DatabaseTables::Uncles => "uncles",
             DatabaseTables::Withdrawals => "withdrawals",
             DatabaseTables::Erc20Transfers => "erc20_transfers",
             DatabaseTables::Erc721Transfers => "erc721_transfers",
             DatabaseTables::Erc1155Transfers => "erc1155_transfers",
             DatabaseTables::DexTrades => "dex_trades",
+            DatabaseTables::DecodeErrors => "decode_errors",
             DatabaseTables::InfoForSync => "infoforsync",
         }
     }
@@ -87,7 +254,17 @@ impl Database {
             .with_password(db_password)
             .with_database(db_name);
 
-        Self { chain, db }
+        Self {
+            chain,
+            datasets: Dataset::all(),
+            db,
+            info_for_sync_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub fn with_datasets(mut self, datasets: HashSet<Dataset>) -> Self {
+        self.datasets = datasets;
+        self
     }
 
     pub async fn get_indexed_blocks(&self) -> HashSet<u32> {
@@ -106,6 +283,47 @@ impl Database {
         blocks
     }
 
+    // Completeness of the stored blocks between `start_block` and `end_block` (inclusive).
+    pub async fn get_blocks_audit(
+        &self,
+        start_block: u32,
+        end_block: u32,
+    ) -> Vec<DatabaseBlockAudit> {
+        let query = format!(
+            "SELECT b.expected_transactions AS expected_transactions, b.expected_uncles AS expected_uncles, b.has_logs AS has_logs, toUInt64(l.logs) AS logs, b.number AS number, toUInt64(t.receipts) AS receipts, toUInt64(t.transactions) AS transactions, toUInt64(u.uncles) AS uncles \
+            FROM (SELECT number, any(transactions) AS expected_transactions, toUInt8(length(any(uncles))) AS expected_uncles, NOT match(any(logs_bloom), '^0x0*$') AS has_logs FROM blocks WHERE chain = {0} AND is_uncle = false AND number BETWEEN {1} AND {2} GROUP BY number) AS b \
+            LEFT JOIN (SELECT block_number, uniqExact(hash) AS transactions, uniqExactIf(hash, gas_used IS NOT NULL) AS receipts FROM transactions WHERE chain = {0} AND block_number BETWEEN {1} AND {2} GROUP BY block_number) AS t ON t.block_number = b.number \
+            LEFT JOIN (SELECT block_number, uniqExact(transaction_hash, log_index) AS logs FROM logs WHERE chain = {0} AND block_number BETWEEN {1} AND {2} GROUP BY block_number) AS l ON l.block_number = b.number \
+            LEFT JOIN (SELECT block_number, uniqExact(uncle_index) AS uncles FROM uncles WHERE chain = {0} AND block_number BETWEEN {1} AND {2} GROUP BY block_number) AS u ON u.block_number = b.number \
+            ORDER BY number",
+            self.chain.id, start_block, end_block
+        );
+
+        match self.db.query(&query).fetch_all::<DatabaseBlockAudit>().await
+        {
+            Ok(blocks) => blocks,
+            Err(e) => {
+                error!("Error fetching blocks audit: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    pub async fn get_last_indexed_block(&self) -> Option<u32> {
+        let query = format!(
+            "SELECT number FROM blocks WHERE chain = {} AND is_uncle = false ORDER BY number DESC LIMIT 1",
+            self.chain.id
+        );
+
+        match self.db.query(&query).fetch_all::<u32>().await {
+            Ok(tokens) => tokens.first().copied(),
+            Err(e) => {
+                error!("Error fetching last indexed block: {}", e);
+                None
+            }
+        }
+    }
+
     pub async fn get_blocks(
         &self,
         skip_count: u32,
@@ -195,6 +413,109 @@ impl Database {
         }
     }
 
+    // Latest authorization signed by `address` for this chain, the one deciding its delegation.
+    // Authorizations rejected by the chain, e.g. for a stale nonce, are not told apart.
+    pub async fn get_delegation(
+        &self,
+        address: &str,
+    ) -> Option<DatabaseAuthorization> {
+        let query = format!(
+            "SELECT * FROM authorizations FINAL WHERE chain = {0} AND authority = ? AND chain_id IN (0, {0}) ORDER BY block_number DESC, transaction_index DESC, authorization_index DESC LIMIT 1",
+            self.chain.id
+        );
+
+        match self
+            .db
+            .query(&query)
+            .bind(address)
+            .fetch_all::<DatabaseAuthorization>()
+            .await
+        {
+            Ok(authorizations) => authorizations.into_iter().next(),
+            Err(e) => {
+                error!("Error fetching delegation of {}: {}", address, e);
+                None
+            }
+        }
+    }
+
+    pub async fn get_block_uncles(
+        &self,
+        number: u64,
+    ) -> Vec<DatabaseUncle> {
+        let query = format!(
+            "SELECT * FROM uncles FINAL WHERE chain = {} AND block_number = {} ORDER BY uncle_index",
+            self.chain.id, number
+        );
+
+        match self.db.query(&query).fetch_all::<DatabaseUncle>().await {
+            Ok(uncles) => uncles,
+            Err(e) => {
+                error!("Error fetching uncles of block {}: {}", number, e);
+                Vec::new()
+            }
+        }
+    }
+
+    pub async fn get_block_withdrawals(
+        &self,
+        number: u64,
+    ) -> Vec<DatabaseWithdrawal> {
+        let query = format!(
+            "SELECT * FROM withdrawals FINAL WHERE chain = {} AND block_number = {} ORDER BY withdrawal_index",
+            self.chain.id, number
+        );
+
+        match self.db.query(&query).fetch_all::<DatabaseWithdrawal>().await
+        {
+            Ok(withdrawals) => withdrawals,
+            Err(e) => {
+                error!(
+                    "Error fetching withdrawals of block {}: {}",
+                    number, e
+                );
+                Vec::new()
+            }
+        }
+    }
+
+    pub async fn get_validator_withdrawal_summary(
+        &self,
+        validator_index: u32,
+    ) -> DatabaseWithdrawalSummary {
+        self.get_withdrawal_summary("validator_index = ?", validator_index)
+            .await
+    }
+
+    pub async fn get_address_withdrawal_summary(
+        &self,
+        address: &str,
+    ) -> DatabaseWithdrawalSummary {
+        self.get_withdrawal_summary("address = ?", address).await
+    }
+
+    // Aggregates the withdrawals matching `filter` with `value` bound to it, all the values are
+    // zero when there are none.
+    async fn get_withdrawal_summary(
+        &self,
+        filter: &str,
+        value: impl Bind,
+    ) -> DatabaseWithdrawalSummary {
+        let query = format!(
+            "SELECT min(block_number) AS first_block, min(timestamp) AS first_timestamp, countIf(withdrawal_type = 'full') AS full_withdrawals, max(block_number) AS last_block, max(timestamp) AS last_timestamp, countIf(withdrawal_type = 'partial') AS partial_withdrawals, sum(amount) AS total_amount, count() AS withdrawals \
+            FROM withdrawals FINAL WHERE chain = {} AND {}",
+            self.chain.id, filter
+        );
+
+        match self.db.query(&query).bind(value).fetch_one().await {
+            Ok(summary) => summary,
+            Err(e) => {
+                error!("Error fetching withdrawal summary: {}", e);
+                DatabaseWithdrawalSummary::default()
+            }
+        }
+    }
+
     pub async fn get_info_for_average_block(&self) -> InfoForAverageBlock {
         let query = format!(
             "SELECT Min(timestamp) as start_time, Max(timestamp) as end_time, Min(number) as start_number, Max(number) as end_number FROM blocks WHERE number IN (SELECT number FROM blocks WHERE chain = {} ORDER BY number DESC LIMIT 50)",
@@ -232,18 +553,13 @@ impl Database {
         }
     }
 
-    pub async fn get_info_for_sync(&self) -> Vec<DatabaseInfoForSync> {
+    pub async fn get_info_for_sync(
+        &self,
+        job: &str,
+    ) -> Vec<DatabaseInfoForSync> {
         info!("get info for sync");
-        let query =
-            "SELECT * FROM infoforsync ORDER BY timestamp DESC LIMIT 2"
-                .to_string();
 
-        match self
-            .db
-            .query(&query)
-            .fetch_all::<DatabaseInfoForSync>()
-            .await
-        {
+        match self.fetch_info_for_sync(job).await {
             Ok(token) => token,
             Err(e) => {
                 error!("Error fetching timestamp and number: {}", e);
@@ -252,9 +568,97 @@ impl Database {
         }
     }
 
-    pub async fn store_data(&self, data: &BlockFetchedData) {
+    async fn fetch_info_for_sync(
+        &self,
+        job: &str,
+    ) -> Result<Vec<DatabaseInfoForSync>, IndexerError> {
+        let query = format!(
+            "SELECT * FROM infoforsync WHERE chain = {} AND job = '{}' ORDER BY timestamp DESC, end_block DESC LIMIT 2",
+            self.chain.id, job
+        );
+
+        Ok(self
+            .db
+            .query(&query)
+            .fetch_all::<DatabaseInfoForSync>()
+            .await?)
+    }
+
+    // Jobs of the chain with a checkpoint.
+    async fn fetch_sync_jobs(&self) -> Result<Vec<String>, IndexerError> {
+        let query = format!(
+            "SELECT DISTINCT job FROM infoforsync WHERE chain = {}",
+            self.chain.id
+        );
+
+        Ok(self.db.query(&query).fetch_all::<String>().await?)
+    }
+
+    // Applies `update` to the latest checkpoint of `job` and stores it when `update` returns true.
+    pub async fn update_info_for_sync<F>(
+        &self,
+        job: &str,
+        update: F,
+    ) -> Result<(), IndexerError>
+    where
+        F: FnOnce(&mut DatabaseInfoForSync) -> bool,
+    {
+        let _lock = self.info_for_sync_lock.lock().await;
+
+        // A checkpoint that can't be read must not be replaced by an empty one.
+        let mut info_for_sync = self
+            .fetch_info_for_sync(job)
+            .await?
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| {
+                DatabaseInfoForSync::new(self.chain.id, job, 0)
+            });
+
+        if !update(&mut info_for_sync) {
+            return Ok(());
+        }
+
+        info_for_sync.timestamp = Utc::now().timestamp() as u32;
+
+        self.store_items(
+            &vec![info_for_sync],
+            DatabaseTables::InfoForSync.as_str(),
+        )
+        .await
+    }
+
+    // Stores a batch so that it is either committed or detectable as half written: the child
+    // tables are written first and the blocks last, as the commit marker of the batch. The caller
+    // advances the checkpoint only after this succeeds, rows of batches interrupted before are
+    // removed by `clean_uncommitted_data` and written again on replay.
+    pub async fn store_data(
+        &self,
+        data: &BlockFetchedData,
+    ) -> Result<(), IndexerError> {
         let mut stores = vec![];
-        if !data.contracts.is_empty() {
+        if !data.authorizations.is_empty()
+            && self.datasets.contains(&Dataset::Authorizations)
+        {
+            let work = tokio::spawn({
+                let authorizations: Vec<DatabaseAuthorization> =
+                    data.authorizations.clone();
+                let db = self.clone();
+                async move {
+                    db.store_items(
+                        &authorizations,
+                        DatabaseTables::Authorizations.as_str(),
+                    )
+                    .await
+                }
+            });
+
+            stores.push(work);
+        }
+
+        if !data.contracts.is_empty()
+            && self.datasets.contains(&Dataset::Contracts)
+        {
             let work = tokio::spawn({
                 let contracts = data.contracts.clone();
                 let db = self.clone();
@@ -269,7 +673,8 @@ impl Database {
             stores.push(work);
         }
 
-        if !data.logs.is_empty() {
+        if !data.logs.is_empty() && self.datasets.contains(&Dataset::Logs)
+        {
             let work = tokio::spawn({
                 let logs = data.logs.clone();
                 let db = self.clone();
@@ -282,7 +687,9 @@ impl Database {
             stores.push(work);
         }
 
-        if !data.traces.is_empty() {
+        if !data.traces.is_empty()
+            && self.datasets.contains(&Dataset::Traces)
+        {
             let work = tokio::spawn({
                 let traces = data.traces.clone();
                 let db = self.clone();
@@ -298,7 +705,9 @@ impl Database {
             stores.push(work);
         }
 
-        if !data.transactions.is_empty() {
+        if !data.transactions.is_empty()
+            && self.datasets.contains(&Dataset::Transactions)
+        {
             let work = tokio::spawn({
                 let transactions = data.transactions.clone();
                 let db = self.clone();
@@ -314,7 +723,27 @@ impl Database {
             stores.push(work);
         }
 
-        if !data.withdrawals.is_empty() {
+        if !data.uncles.is_empty()
+            && self.datasets.contains(&Dataset::Uncles)
+        {
+            let work = tokio::spawn({
+                let uncles: Vec<DatabaseUncle> = data.uncles.clone();
+                let db = self.clone();
+                async move {
+                    db.store_items(
+                        &uncles,
+                        DatabaseTables::Uncles.as_str(),
+                    )
+                    .await
+                }
+            });
+
+            stores.push(work);
+        }
+
+        if !data.withdrawals.is_empty()
+            && self.datasets.contains(&Dataset::Withdrawals)
+        {
             let work = tokio::spawn({
                 let withdrawals: Vec<DatabaseWithdrawal> =
                     data.withdrawals.clone();
@@ -331,7 +760,9 @@ impl Database {
             stores.push(work);
         }
 
-        if !data.erc20_transfers.is_empty() {
+        if !data.erc20_transfers.is_empty()
+            && self.datasets.contains(&Dataset::Erc20)
+        {
             let work = tokio::spawn({
                 let transfers: Vec<DatabaseERC20Transfer> =
                     data.erc20_transfers.clone();
@@ -348,7 +779,9 @@ impl Database {
             stores.push(work);
         }
 
-        if !data.erc721_transfers.is_empty() {
+        if !data.erc721_transfers.is_empty()
+            && self.datasets.contains(&Dataset::Erc721)
+        {
             let work = tokio::spawn({
                 let transfers: Vec<DatabaseERC721Transfer> =
                     data.erc721_transfers.clone();
@@ -365,7 +798,9 @@ impl Database {
             stores.push(work);
         }
 
-        if !data.erc1155_transfers.is_empty() {
+        if !data.erc1155_transfers.is_empty()
+            && self.datasets.contains(&Dataset::Erc1155)
+        {
             let work = tokio::spawn({
                 let transfers: Vec<DatabaseERC1155Transfer> =
                     data.erc1155_transfers.clone();
@@ -382,7 +817,9 @@ impl Database {
             stores.push(work);
         }
 
-        if !data.dex_trades.is_empty() {
+        if !data.dex_trades.is_empty()
+            && self.datasets.contains(&Dataset::DexTrades)
+        {
             let work = tokio::spawn({
                 let trades: Vec<DatabaseDexTrade> =
                     data.dex_trades.clone();
@@ -399,13 +836,32 @@ impl Database {
             stores.push(work);
         }
 
-        let res = join_all(stores).await;
+        if !data.decode_errors.is_empty() {
+            let work = tokio::spawn({
+                let decode_errors: Vec<DatabaseDecodeError> =
+                    data.decode_errors.clone();
+                let db = self.clone();
+                async move {
+                    db.store_items(
+                        &decode_errors,
+                        DatabaseTables::DecodeErrors.as_str(),
+                    )
+                    .await
+                }
+            });
 
-        let errored: Vec<_> =
-            res.iter().filter(|res| res.is_err()).collect();
+            stores.push(work);
+        }
 
-        if !errored.is_empty() {
-            panic!("failed to store all chain primitive elements")
+        for res in join_all(stores).await {
+            match res {
+                Ok(res) => res?,
+                Err(err) => {
+                    return Err(IndexerError::Storage(
+                        clickhouse::error::Error::Custom(err.to_string()),
+                    ))
+                }
+            }
         }
 
         if !data.blocks.is_empty() {
@@ -413,39 +869,240 @@ impl Database {
                 &data.blocks,
                 DatabaseTables::Blocks.as_str(),
             )
-            .await;
+            .await?;
         }
 
         info!(
-            "Inserted: contracts ({}) logs ({}) traces ({}) transactions ({}) withdrawals ({}) erc20 ({}) erc721 ({}) erc1155 ({}) dex_trades ({}) in ({}) blocks.",
+            "Inserted: authorizations ({}) contracts ({}) logs ({}) traces ({}) transactions ({}) uncles ({}) withdrawals ({}) erc20 ({}) erc721 ({}) erc1155 ({}) dex_trades ({}) decode_errors ({}) in ({}) blocks.",
+            data.authorizations.len(),
             data.contracts.len(),
             data.logs.len(),
             data.traces.len(),
             data.transactions.len(),
+            data.uncles.len(),
             data.withdrawals.len(),
             data.erc20_transfers.len(),
             data.erc721_transfers.len(),
             data.erc1155_transfers.len(),
             data.dex_trades.len(),
+            data.decode_errors.len(),
             data.blocks.len()
         );
+
+        Ok(())
     }
 
-    pub async fn store_items<T>(&self, items: &Vec<T>, table: &str)
+    // Inserts the items retrying transient failures. A retried insert may write some rows twice,
+    // which the ReplacingMergeTree tables deduplicate.
+    pub async fn store_items<T>(
+        &self,
+        items: &Vec<T>,
+        table: &str,
+    ) -> Result<(), IndexerError>
+    where
+        T: Row + Serialize,
+    {
+        let mut backoff = STORE_RETRY_MIN_BACKOFF;
+        let mut attempt = 1;
+
+        loop {
+            match self.insert_items(items, table).await {
+                Ok(_) => return Ok(()),
+                Err(err)
+                    if err.is_transient() && attempt < STORE_ATTEMPTS =>
+                {
+                    warn!(
+                        "Unable to store {} into database (attempt {}/{}), retrying in {:?}: {}",
+                        table, attempt, STORE_ATTEMPTS, backoff, err
+                    );
+
+                    sleep(backoff).await;
+
+                    backoff = (backoff * 2).min(STORE_RETRY_MAX_BACKOFF);
+                    attempt += 1;
+                }
+                Err(err) => {
+                    error!(
+                        "Unable to store {} into database: {}",
+                        table, err
+                    );
+                    return Err(err);
+                }
+            }
+        }
+    }
+
+    async fn insert_items<T>(
+        &self,
+        items: &Vec<T>,
+        table: &str,
+    ) -> Result<(), IndexerError>
     where
         T: Row + Serialize,
     {
-        let mut inserter = self.db.inserter(table).unwrap();
+        let mut inserter = self.db.inserter(table)?;
 
         for item in items {
-            inserter.write(item).await.unwrap();
+            inserter.write(item).await?;
         }
 
-        match inserter.end().await {
-            Ok(_) => (),
-            Err(err) => {
-                error!("{}", err);
-                panic!("Unable to store {} into database", table)
+        inserter.end().await?;
+
+        Ok(())
+    }
+
+    // Stored rows of `table` between `start_block` and `end_block` (inclusive) matching `filter`,
+    // read back to derive other tables without fetching the blocks again.
+    pub async fn get_range_items<T>(
+        &self,
+        table: DatabaseTables,
+        start_block: u32,
+        end_block: u32,
+        filter: &str,
+    ) -> Result<Vec<T>, IndexerError>
+    where
+        T: Row + for<'de> Deserialize<'de>,
+    {
+        let query = format!(
+            "SELECT * FROM {} FINAL WHERE chain = {} AND block_number BETWEEN {} AND {} AND {}",
+            table.as_str(),
+            self.chain.id,
+            start_block,
+            end_block,
+            filter
+        );
+
+        Ok(self.db.query(&query).fetch_all::<T>().await?)
+    }
+
+    // Deletes the rows of `table` between `start_block` and `end_block` (inclusive) matching
+    // `filter`.
+    pub async fn delete_range(
+        &self,
+        table: DatabaseTables,
+        start_block: u32,
+        end_block: u32,
+        filter: &str,
+    ) -> Result<(), IndexerError> {
+        let query = format!(
+            "ALTER TABLE {} DELETE WHERE chain = {} AND block_number BETWEEN {} AND {} AND {}",
+            table.as_str(),
+            self.chain.id,
+            start_block,
+            end_block,
+            filter
+        );
+
+        // Wait for the delete, the rows inserted afterwards must not be removed with it.
+        let db = self.db.clone().with_option("mutations_sync", "1");
+
+        Ok(db.query(&query).execute().await?)
+    }
+
+    // Removes the rows of batches that were interrupted before their blocks were stored: child
+    // rows after the checkpoint of `job` (up to `last_block` for bounded jobs) or of its missing
+    // blocks that have no block stored. The ranges of the other bounded jobs are left alone as
+    // they may still be running, and so are the rows of the logs mode, which stores no blocks.
+    pub async fn clean_uncommitted_data(
+        &self,
+        job: &str,
+        last_block: Option<u32>,
+    ) {
+        let info_for_sync = match self.get_info_for_sync(job).await.first()
+        {
+            Some(info_for_sync) => info_for_sync.clone(),
+            None => return,
+        };
+
+        let mut uncommitted = match last_block {
+            Some(last_block) => format!(
+                "block_number BETWEEN {} AND {}",
+                info_for_sync.end_block, last_block
+            ),
+            None => format!("block_number >= {}", info_for_sync.end_block),
+        };
+
+        if !info_for_sync.missing_blocks.is_empty() {
+            uncommitted = format!(
+                "({} OR block_number IN ({}))",
+                uncommitted,
+                info_for_sync
+                    .missing_blocks
+                    .iter()
+                    .map(|block| block.to_string())
+                    .collect::<Vec<String>>()
+                    .join(",")
+            );
+        }
+
+        let jobs = match self.fetch_sync_jobs().await {
+            Ok(jobs) => jobs,
+            Err(e) => {
+                error!("Error fetching the sync jobs, skipping the cleanup: {}", e);
+                return;
+            }
+        };
+
+        let mut other_ranges = String::new();
+        let mut logs_ranges = String::new();
+        let mut has_unbounded_logs_job = false;
+
+        for other_job in jobs.iter().filter(|other_job| *other_job != job)
+        {
+            let skipped = sync_job_range(other_job).map(|(start, end)| {
+                format!(
+                    " AND block_number NOT BETWEEN {} AND {}",
+                    start, end
+                )
+            });
+
+            match (is_logs_sync_job(other_job), skipped) {
+                (true, Some(skipped)) => logs_ranges.push_str(&skipped),
+                (true, None) => has_unbounded_logs_job = true,
+                (false, Some(skipped)) => other_ranges.push_str(&skipped),
+                (false, None) => (),
+            }
+        }
+
+        // Wait for the deletes, the batches replayed afterwards must not be removed with them.
+        let db = self.db.clone().with_option("mutations_sync", "1");
+
+        for table in DatabaseTables::children() {
+            let skipped = match table.is_written_by_logs() {
+                // a logs job following the tip may write at any height
+                true if has_unbounded_logs_job => continue,
+                true => format!("{}{}", other_ranges, logs_ranges),
+                false => other_ranges.clone(),
+            };
+
+            // genesis allocations have no block row but are committed
+            let genesis = match table {
+                DatabaseTables::Contracts => {
+                    " AND transaction_hash NOT LIKE '%_GENESIS_%'"
+                }
+                DatabaseTables::Transactions => {
+                    " AND hash NOT LIKE '%_GENESIS_%'"
+                }
+                _ => "",
+            };
+
+            let query = format!(
+                "ALTER TABLE {} DELETE WHERE chain = {} AND {}{}{} AND block_number NOT IN (SELECT number FROM blocks WHERE chain = {} AND is_uncle = false)",
+                table.as_str(),
+                self.chain.id,
+                uncommitted,
+                genesis,
+                skipped,
+                self.chain.id
+            );
+
+            match db.query(&query).execute().await {
+                Ok(_) => (),
+                Err(e) => error!(
+                    "Error cleaning uncommitted rows of {}: {}",
+                    table.as_str(),
+                    e
+                ),
             }
         }
     }
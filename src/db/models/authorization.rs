@@ -0,0 +1,188 @@
+use clickhouse::Row;
+use ethers::{
+    types::{Address, Signature, Transaction, H256, U256, U64},
+    utils::{keccak256, rlp::RlpStream},
+};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+
+use crate::utils::format::{format_address, format_hash, SerU256};
+
+// Prefix of the messages signed by the authorities of EIP-7702 authorizations.
+const AUTHORIZATION_MAGIC: u8 = 0x05;
+
+// Half of the order of secp256k1, signatures with a greater `s` are rejected (EIP-2).
+const SECP256K1N_HALF: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4,
+    0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
+// Entry of the `authorizationList` of a set code transaction, ethers leaves it in the other fields.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcAuthorization {
+    address: Address,
+    chain_id: U256,
+    nonce: U64,
+    r: U256,
+    s: U256,
+    y_parity: U64,
+}
+
+impl RpcAuthorization {
+    // Hash signed by the authority, keccak256(MAGIC || rlp([chain_id, address, nonce])).
+    fn signature_hash(&self) -> H256 {
+        let mut stream = RlpStream::new_list(3);
+        stream.append(&self.chain_id);
+        stream.append(&self.address);
+        stream.append(&self.nonce);
+
+        let mut message = vec![AUTHORIZATION_MAGIC];
+        message.extend_from_slice(&stream.out());
+
+        H256::from(keccak256(message))
+    }
+
+    // Signer of the authorization, none when the signature is invalid and the entry is skipped.
+    fn recover_authority(&self) -> Option<Address> {
+        if self.s > U256::from_big_endian(&SECP256K1N_HALF) {
+            return None;
+        }
+
+        let signature =
+            Signature { r: self.r, s: self.s, v: self.y_parity.as_u64() };
+
+        signature.recover(self.signature_hash()).ok()
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Row, Serialize, Deserialize)]
+pub struct DatabaseAuthorization {
+    pub address: String,
+    pub authority: Option<String>,
+    pub authorization_index: u16,
+    pub block_number: u32,
+    pub chain: u64,
+    #[serde_as(as = "SerU256")]
+    pub chain_id: U256,
+    pub nonce: u64,
+    pub timestamp: u32,
+    pub transaction_hash: String,
+    pub transaction_index: u16,
+}
+
+impl DatabaseAuthorization {
+    // Authorizations of a set code transaction, none for the other types.
+    pub fn from_rpc(
+        transaction: &Transaction,
+        chain: u64,
+        timestamp: u32,
+    ) -> Vec<Self> {
+        let authorizations: Vec<RpcAuthorization> = transaction
+            .other
+            .get_deserialized("authorizationList")
+            .and_then(Result::ok)
+            .unwrap_or_default();
+
+        authorizations
+            .iter()
+            .enumerate()
+            .map(|(authorization_index, authorization)| Self {
+                address: format_address(authorization.address),
+                authority: authorization
+                    .recover_authority()
+                    .map(format_address),
+                authorization_index: authorization_index as u16,
+                block_number: transaction.block_number.unwrap().as_u32(),
+                chain,
+                chain_id: authorization.chain_id,
+                nonce: authorization.nonce.as_u64(),
+                timestamp,
+                transaction_hash: format_hash(transaction.hash),
+                transaction_index: transaction
+                    .transaction_index
+                    .unwrap()
+                    .as_u64() as u16,
+            })
+            .collect()
+    }
+
+    // Delegations to the zero address clear the code of the authority.
+    pub fn is_revocation(&self) -> bool {
+        self.address == format_address(Address::zero())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::signers::{LocalWallet, Signer};
+
+    // Order of secp256k1.
+    const SECP256K1N: &str =
+        "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141";
+
+    fn wallet() -> LocalWallet {
+        "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+            .parse()
+            .unwrap()
+    }
+
+    fn signed_authorization(wallet: &LocalWallet) -> RpcAuthorization {
+        let mut authorization = RpcAuthorization {
+            address: "0x63c0c19a282a1b52b07dd5a65b58948a07dae32b"
+                .parse()
+                .unwrap(),
+            chain_id: U256::one(),
+            nonce: U64::from(7),
+            r: U256::zero(),
+            s: U256::zero(),
+            y_parity: U64::zero(),
+        };
+
+        let signature =
+            wallet.sign_hash(authorization.signature_hash()).unwrap();
+
+        authorization.r = signature.r;
+        authorization.s = signature.s;
+        authorization.y_parity = U64::from(signature.v - 27);
+        authorization
+    }
+
+    #[test]
+    fn recovers_the_authority() {
+        let wallet = wallet();
+
+        assert_eq!(
+            signed_authorization(&wallet).recover_authority(),
+            Some(wallet.address())
+        );
+    }
+
+    #[test]
+    fn other_fields_recover_another_authority() {
+        let wallet = wallet();
+        let mut authorization = signed_authorization(&wallet);
+        authorization.nonce = U64::from(8);
+
+        assert_ne!(
+            authorization.recover_authority(),
+            Some(wallet.address())
+        );
+    }
+
+    #[test]
+    fn high_s_signatures_are_rejected() {
+        let mut authorization = signed_authorization(&wallet());
+
+        // The same signature with the other `s`, valid before EIP-2.
+        authorization.s = U256::from_str_radix(SECP256K1N, 16).unwrap()
+            - authorization.s;
+        authorization.y_parity =
+            U64::from(1 - authorization.y_parity.as_u64());
+
+        assert_eq!(authorization.recover_authority(), None);
+    }
+}
@@ -1,12 +1,15 @@
 use clickhouse::Row;
-use ethers::types::Block;
+use ethers::types::{Block, H256, U64};
 use primitive_types::U256;
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 
-use crate::utils::format::{
-    format_address, format_bytes, format_bytes_slice, format_hash,
-    format_nonce, SerU256,
+use crate::{
+    chains::rewards::BlockReward,
+    utils::format::{
+        format_address, format_bytes, format_bytes_slice, format_hash,
+        format_nonce, SerU256,
+    },
 };
 
 type StrArr = Vec<String>;
@@ -17,11 +20,14 @@ pub struct DatabaseBlock {
     #[serde_as(as = "SerU256")]
     pub base_block_reward: U256,
     pub base_fee_per_gas: Option<u64>,
+    pub blob_gas_used: Option<u64>,
+    pub blob_transactions: u16,
     #[serde_as(as = "SerU256")]
     pub burned: U256,
     pub chain: u64,
     #[serde_as(as = "SerU256")]
     pub difficulty: U256,
+    pub excess_blob_gas: Option<u64>,
     pub extra_data: String,
     pub gas_limit: u32,
     pub gas_used: u32,
@@ -32,6 +38,7 @@ pub struct DatabaseBlock {
     pub mix_hash: Option<String>,
     pub nonce: String,
     pub number: u32,
+    pub parent_beacon_block_root: Option<String>,
     pub parent_hash: String,
     pub receipts_root: String,
     pub sha3_uncles: String,
@@ -47,6 +54,7 @@ pub struct DatabaseBlock {
     #[serde_as(as = "SerU256")]
     pub uncle_rewards: U256,
     pub uncles: StrArr,
+    pub withdrawals: Option<u16>,
     pub withdrawals_root: Option<String>,
 }
 
@@ -66,9 +74,20 @@ impl DatabaseBlock {
         Self {
             base_block_reward: U256::zero(),
             base_fee_per_gas,
+            blob_gas_used: block
+                .other
+                .get_deserialized::<U64>("blobGasUsed")
+                .and_then(Result::ok)
+                .map(|blob_gas_used| blob_gas_used.as_u64()),
+            blob_transactions: 0,
             burned: U256::zero(),
             chain,
             difficulty: block.difficulty,
+            excess_blob_gas: block
+                .other
+                .get_deserialized::<U64>("excessBlobGas")
+                .and_then(Result::ok)
+                .map(|excess_blob_gas| excess_blob_gas.as_u64()),
             extra_data: format_bytes(&block.extra_data),
             gas_limit: block.gas_limit.as_usize() as u32,
             gas_used: block.gas_used.as_usize() as u32,
@@ -81,6 +100,11 @@ impl DatabaseBlock {
             mix_hash: block.mix_hash.map(format_hash),
             nonce: format_nonce(block.nonce.unwrap()),
             number: block.number.unwrap().as_usize() as u32,
+            parent_beacon_block_root: block
+                .other
+                .get_deserialized::<H256>("parentBeaconBlockRoot")
+                .and_then(Result::ok)
+                .map(format_hash),
             parent_hash: format_hash(block.parent_hash),
             receipts_root: format_hash(block.receipts_root),
             sha3_uncles: format_hash(block.uncles_hash),
@@ -98,30 +122,33 @@ impl DatabaseBlock {
                 .map(format_hash)
                 .collect(),
             uncle_rewards: U256::zero(),
+            withdrawals: block
+                .withdrawals
+                .as_ref()
+                .map(|withdrawals| withdrawals.len() as u16),
             withdrawals_root,
         }
     }
 
-    pub fn add_rewards(
-        &mut self,
-        base_block_reward: U256,
-        burned: U256,
-        total_fee_reward: U256,
-        uncle_rewards: U256,
-    ) {
-        self.base_block_reward = base_block_reward;
+    // The fee reward only holds the priority fees earned by the producer, the burned base fees
+    // are stored apart.
+    pub fn add_rewards(&mut self, reward: &BlockReward, burned: U256) {
+        self.base_block_reward = reward.base_reward;
         self.burned = burned;
-        self.total_fee_reward = total_fee_reward;
-        self.uncle_rewards = uncle_rewards;
+        self.total_fee_reward = reward.fees;
+        self.uncle_rewards = reward.uncles_reward;
     }
 
     pub fn new() -> Self {
         Self {
             base_block_reward: U256::zero(),
             base_fee_per_gas: None,
+            blob_gas_used: None,
+            blob_transactions: 0,
             burned: U256::zero(),
             chain: 0,
             difficulty: U256::zero(),
+            excess_blob_gas: None,
             extra_data: "".to_string(),
             gas_limit: 0,
             gas_used: 0,
@@ -132,6 +159,7 @@ impl DatabaseBlock {
             mix_hash: None,
             nonce: "".to_string(),
             number: 0,
+            parent_beacon_block_root: None,
             parent_hash: "".to_string(),
             receipts_root: "".to_string(),
             sha3_uncles: "".to_string(),
@@ -144,6 +172,7 @@ impl DatabaseBlock {
             transactions_root: "".to_string(),
             uncles: Vec::new(),
             uncle_rewards: U256::zero(),
+            withdrawals: None,
             withdrawals_root: None,
         }
     }
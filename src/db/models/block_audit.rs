@@ -0,0 +1,46 @@
+use clickhouse::Row;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::configs::Dataset;
+
+// Stored block compared with the rows of its transactions, logs and uncles.
+#[derive(Debug, Clone, Row, Serialize, Deserialize)]
+pub struct DatabaseBlockAudit {
+    pub expected_transactions: u16,
+    pub expected_uncles: u8,
+    pub has_logs: bool,
+    pub logs: u64,
+    pub number: u32,
+    pub receipts: u64,
+    pub transactions: u64,
+    pub uncles: u64,
+}
+
+impl DatabaseBlockAudit {
+    // A block is partial when some of its transactions, receipts or uncles are missing, or when
+    // its bloom announces logs but none were stored. Genesis allocations are stored as
+    // transactions without receipt, so only the announced transactions are expected to have one.
+    // Only the indexed datasets are checked.
+    pub fn is_partial(&self, datasets: &HashSet<Dataset>) -> bool {
+        let expected_transactions = self.expected_transactions as u64;
+
+        if datasets.contains(&Dataset::Uncles)
+            && self.uncles < self.expected_uncles as u64
+        {
+            return true;
+        }
+
+        if !datasets.contains(&Dataset::Transactions) {
+            return false;
+        }
+
+        self.transactions < expected_transactions
+            || (datasets.contains(&Dataset::Receipts)
+                && self.receipts
+                    < self.transactions.min(expected_transactions))
+            || (datasets.contains(&Dataset::Logs)
+                && self.has_logs
+                && self.logs == 0)
+    }
+}
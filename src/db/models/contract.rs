@@ -2,6 +2,7 @@ use clickhouse::Row;
 use ethers::types::TransactionReceipt;
 use serde::{Deserialize, Serialize};
 
+use super::transaction::{DatabaseTransaction, TransactionStatus};
 use crate::utils::format::{format_address, format_hash};
 
 #[derive(Debug, Clone, Row, Serialize, Deserialize)]
@@ -25,4 +26,21 @@ impl DatabaseContract {
             transaction_hash: format_hash(receipt.transaction_hash),
         }
     }
+
+    // Contract created by a stored transaction, as found in its receipt.
+    pub fn from_transaction(
+        transaction: &DatabaseTransaction,
+    ) -> Option<Self> {
+        if transaction.status != Some(TransactionStatus::Success) {
+            return None;
+        }
+
+        Some(Self {
+            block_number: transaction.block_number,
+            chain: transaction.chain,
+            contract_address: transaction.contract_created.clone()?,
+            creator: transaction.from.clone(),
+            transaction_hash: transaction.hash.clone(),
+        })
+    }
 }
@@ -0,0 +1,33 @@
+use clickhouse::Row;
+use serde::{Deserialize, Serialize};
+
+use super::log::DatabaseLog;
+use crate::errors::IndexerError;
+
+// Log matching a known event signature whose values couldn't be decoded.
+#[derive(Debug, Clone, Row, Serialize, Deserialize)]
+pub struct DatabaseDecodeError {
+    pub address: String,
+    pub block_number: u32,
+    pub chain: u64,
+    pub error: String,
+    pub log_index: u16,
+    pub timestamp: u32,
+    pub topic0: String,
+    pub transaction_hash: String,
+}
+
+impl DatabaseDecodeError {
+    pub fn from_log(log: &DatabaseLog, error: &IndexerError) -> Self {
+        Self {
+            address: log.address.clone(),
+            block_number: log.block_number,
+            chain: log.chain,
+            error: error.to_string(),
+            log_index: log.log_index,
+            timestamp: log.timestamp,
+            topic0: log.topic0.clone(),
+            transaction_hash: log.transaction_hash.clone(),
+        }
+    }
+}
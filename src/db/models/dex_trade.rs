@@ -1,10 +1,18 @@
 use clickhouse::Row;
-use ethers::abi::{ethabi, ParamType};
-use primitive_types::{H256, U256};
+use ethers::abi::ParamType;
+use primitive_types::{H160, U256};
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 
-use crate::utils::format::{decode_bytes, format_address, SerU256};
+use crate::{
+    errors::IndexerError,
+    utils::{
+        decode::{
+            decode_data, decode_topic_address, token_int, token_uint,
+        },
+        format::{format_address, SerU256},
+    },
+};
 
 use super::log::DatabaseLog;
 
@@ -30,100 +38,41 @@ pub struct DatabaseDexTrade {
 }
 
 impl DatabaseDexTrade {
-    pub fn from_v2_rpc(log: &DatabaseLog) -> Self {
-        let maker_bytes = array_bytes::hex_n_into::<String, H256, 32>(
-            log.topic1.clone().unwrap(),
-        )
-        .unwrap();
+    pub fn from_v2_rpc(log: &DatabaseLog) -> Result<Self, IndexerError> {
+        let maker = decode_topic_address(log.topic1.as_deref())?;
 
-        let receiver_bytes = array_bytes::hex_n_into::<String, H256, 32>(
-            log.topic2.clone().unwrap(),
-        )
-        .unwrap();
+        let receiver = decode_topic_address(log.topic2.as_deref())?;
 
-        let maker_tokens =
-            ethabi::decode(&[ParamType::Address], maker_bytes.as_bytes())
-                .unwrap();
-
-        let maker = maker_tokens.first().unwrap();
-
-        let receiver_tokens = ethabi::decode(
-            &[ParamType::Address],
-            receiver_bytes.as_bytes(),
-        )
-        .unwrap();
-
-        let receiver = receiver_tokens.first().unwrap();
-
-        let log_data = decode_bytes(log.data.clone());
-
-        let values_tokens = ethabi::decode(
+        let values_tokens = decode_data(
+            &log.data,
             &[
                 ParamType::Uint(256),
                 ParamType::Uint(256),
                 ParamType::Uint(256),
                 ParamType::Uint(256),
             ],
-            &log_data[..],
-        )
-        .unwrap();
+        )?;
 
-        let token0_amount =
-            values_tokens[2].to_owned().into_uint().unwrap();
+        let token0_amount = token_uint(values_tokens[2].to_owned())?;
 
-        let token1_amount =
-            values_tokens[3].to_owned().into_uint().unwrap();
+        let token1_amount = token_uint(values_tokens[3].to_owned())?;
 
-        Self {
-            address: log.address.clone(),
-            block_number: log.block_number,
-            chain: log.chain,
-            log_index: log.log_index,
-            log_type: log.log_type.clone(),
-            maker: format_address(
-                maker.to_owned().into_address().unwrap(),
-            ),
-            pair: log.address.clone(),
-            receiver: format_address(
-                receiver.to_owned().into_address().unwrap(),
-            ),
-            removed: log.removed,
-            timestamp: log.timestamp,
+        Ok(Self::from_log(
+            log,
+            maker,
+            receiver,
             token0_amount,
             token1_amount,
-            transaction_hash: log.transaction_hash.clone(),
-            transaction_log_index: log.transaction_log_index,
-        }
+        ))
     }
 
-    pub fn from_v3_rpc(log: &DatabaseLog) -> Self {
-        let maker_bytes = array_bytes::hex_n_into::<String, H256, 32>(
-            log.topic1.clone().unwrap(),
-        )
-        .unwrap();
-
-        let receiver_bytes = array_bytes::hex_n_into::<String, H256, 32>(
-            log.topic2.clone().unwrap(),
-        )
-        .unwrap();
-
-        let maker_tokens =
-            ethabi::decode(&[ParamType::Address], maker_bytes.as_bytes())
-                .unwrap();
-
-        let maker = maker_tokens.first().unwrap();
+    pub fn from_v3_rpc(log: &DatabaseLog) -> Result<Self, IndexerError> {
+        let maker = decode_topic_address(log.topic1.as_deref())?;
 
-        let receiver_tokens = ethabi::decode(
-            &[ParamType::Address],
-            receiver_bytes.as_bytes(),
-        )
-        .unwrap();
+        let receiver = decode_topic_address(log.topic2.as_deref())?;
 
-        let receiver = receiver_tokens.first().unwrap();
-
-        let log_data = decode_bytes(log.data.clone());
-
-        let values_tokens = ethabi::decode(
+        let values_tokens = decode_data(
+            &log.data,
             &[
                 ParamType::Int(256),
                 ParamType::Int(256),
@@ -131,29 +80,37 @@ impl DatabaseDexTrade {
                 ParamType::Uint(128),
                 ParamType::Int(24),
             ],
-            &log_data[..],
-        )
-        .unwrap();
+        )?;
+
+        let token0_amount = token_int(values_tokens[0].to_owned())?;
 
-        let token0_amount =
-            values_tokens[0].to_owned().into_int().unwrap();
+        let token1_amount = token_int(values_tokens[1].to_owned())?;
 
-        let token1_amount =
-            values_tokens[1].to_owned().into_int().unwrap();
+        Ok(Self::from_log(
+            log,
+            maker,
+            receiver,
+            token0_amount,
+            token1_amount,
+        ))
+    }
 
+    fn from_log(
+        log: &DatabaseLog,
+        maker: H160,
+        receiver: H160,
+        token0_amount: U256,
+        token1_amount: U256,
+    ) -> Self {
         Self {
             address: log.address.clone(),
             block_number: log.block_number,
             chain: log.chain,
             log_index: log.log_index,
             log_type: log.log_type.clone(),
-            maker: format_address(
-                maker.to_owned().into_address().unwrap(),
-            ),
+            maker: format_address(maker),
             pair: log.address.clone(),
-            receiver: format_address(
-                receiver.to_owned().into_address().unwrap(),
-            ),
+            receiver: format_address(receiver),
             removed: log.removed,
             timestamp: log.timestamp,
             token0_amount,
@@ -1,11 +1,16 @@
 use clickhouse::Row;
-use ethers::abi::{ethabi, ParamType};
-use primitive_types::{H256, U256};
+use primitive_types::U256;
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 
 use super::log::DatabaseLog;
-use crate::utils::format::{format_address, SerU256};
+use crate::{
+    errors::IndexerError,
+    utils::{
+        decode::decode_topic_address,
+        format::{format_address, SerU256},
+    },
+};
 
 #[serde_as]
 #[derive(Debug, Clone, Row, Serialize, Deserialize)]
@@ -34,141 +39,45 @@ impl DatabaseERC1155Transfer {
         log: &DatabaseLog,
         id: U256,
         amount: U256,
-    ) -> Self {
-        let operator_bytes = array_bytes::hex_n_into::<String, H256, 32>(
-            log.topic1.clone().unwrap(),
-        )
-        .unwrap();
-
-        let from_address_bytes =
-            array_bytes::hex_n_into::<String, H256, 32>(
-                log.topic2.clone().unwrap(),
-            )
-            .unwrap();
-
-        let to_address_bytes =
-            array_bytes::hex_n_into::<String, H256, 32>(
-                log.topic3.clone().unwrap(),
-            )
-            .unwrap();
-
-        let operator_tokens = ethabi::decode(
-            &[ParamType::Address],
-            operator_bytes.as_bytes(),
-        )
-        .unwrap();
-
-        let operator = operator_tokens.first().unwrap();
-
-        let from_address_tokens = ethabi::decode(
-            &[ParamType::Address],
-            from_address_bytes.as_bytes(),
-        )
-        .unwrap();
-
-        let from_address = from_address_tokens.first().unwrap();
-
-        let to_address_tokens = ethabi::decode(
-            &[ParamType::Address],
-            to_address_bytes.as_bytes(),
-        )
-        .unwrap();
-
-        let to_address = to_address_tokens.first().unwrap();
-
-        Self {
-            address: log.address.clone(),
-            amounts: vec![amount],
-            block_number: log.block_number,
-            chain: log.chain,
-            from: format_address(
-                from_address.to_owned().into_address().unwrap(),
-            ),
-            ids: vec![id],
-            log_index: log.log_index,
-            log_type: log.log_type.clone(),
-            operator: format_address(
-                operator.to_owned().into_address().unwrap(),
-            ),
-            removed: log.removed,
-            timestamp: log.timestamp,
-            to: format_address(
-                to_address.to_owned().into_address().unwrap(),
-            ),
-            token_address: log.address.clone(),
-            transaction_hash: log.transaction_hash.clone(),
-            transaction_log_index: log.transaction_log_index,
-        }
+    ) -> Result<Self, IndexerError> {
+        Self::from_log(log, vec![id], vec![amount])
     }
 
     pub fn from_batch_rpc(
         log: &DatabaseLog,
         ids: Vec<U256>,
         amounts: Vec<U256>,
-    ) -> Self {
-        let operator_bytes = array_bytes::hex_n_into::<String, H256, 32>(
-            log.topic1.clone().unwrap(),
-        )
-        .unwrap();
-
-        let from_address_bytes =
-            array_bytes::hex_n_into::<String, H256, 32>(
-                log.topic2.clone().unwrap(),
-            )
-            .unwrap();
-
-        let to_address_bytes =
-            array_bytes::hex_n_into::<String, H256, 32>(
-                log.topic3.clone().unwrap(),
-            )
-            .unwrap();
-
-        let operator_tokens = ethabi::decode(
-            &[ParamType::Address],
-            operator_bytes.as_bytes(),
-        )
-        .unwrap();
-
-        let operator = operator_tokens.first().unwrap();
-
-        let from_address_tokens = ethabi::decode(
-            &[ParamType::Address],
-            from_address_bytes.as_bytes(),
-        )
-        .unwrap();
+    ) -> Result<Self, IndexerError> {
+        Self::from_log(log, ids, amounts)
+    }
 
-        let from_address = from_address_tokens.first().unwrap();
+    fn from_log(
+        log: &DatabaseLog,
+        ids: Vec<U256>,
+        amounts: Vec<U256>,
+    ) -> Result<Self, IndexerError> {
+        let operator = decode_topic_address(log.topic1.as_deref())?;
 
-        let to_address_tokens = ethabi::decode(
-            &[ParamType::Address],
-            to_address_bytes.as_bytes(),
-        )
-        .unwrap();
+        let from_address = decode_topic_address(log.topic2.as_deref())?;
 
-        let to_address = to_address_tokens.first().unwrap();
+        let to_address = decode_topic_address(log.topic3.as_deref())?;
 
-        Self {
+        Ok(Self {
             address: log.address.clone(),
             amounts,
             block_number: log.block_number,
             chain: log.chain,
-            from: format_address(
-                from_address.to_owned().into_address().unwrap(),
-            ),
+            from: format_address(from_address),
             ids,
             log_index: log.log_index,
             log_type: log.log_type.clone(),
-            operator: format_address(
-                operator.to_owned().into_address().unwrap(),
-            ),
+            operator: format_address(operator),
             removed: log.removed,
             timestamp: log.timestamp,
-            to: format_address(
-                to_address.to_owned().into_address().unwrap(),
-            ),
+            to: format_address(to_address),
             token_address: log.address.clone(),
             transaction_hash: log.transaction_hash.clone(),
             transaction_log_index: log.transaction_log_index,
-        }
+        })
     }
 }
@@ -8,7 +8,90 @@ use crate::utils::format::{format_address, format_bytes, format_hash};
 #[serde_as]
 #[derive(Debug, Clone, Row, Serialize, Deserialize)]
 pub struct DatabaseInfoForSync {
+    pub chain: u64,
     pub end_block: u32,
+    pub job: String,
     pub missing_blocks: Vec<u32>,
     pub timestamp: u32,
 }
+
+// Checkpoint of the job following the chain tip, shared by the backfill and the live pipeline.
+pub const TIP_SYNC_JOB: &str = "tip";
+
+// Checkpoint of a bounded job indexing exactly the blocks from `start_block` to `end_block`.
+pub fn range_sync_job(start_block: u32, end_block: u32) -> String {
+    format!("range_{}_{}", start_block, end_block)
+}
+
+// Checkpoint of a logs mode job, scoped by its filter and, when bounded, by its range.
+pub fn logs_sync_job(
+    filter_id: &str,
+    range: Option<(u32, u32)>,
+) -> String {
+    match range {
+        Some((start_block, end_block)) => {
+            format!("logs_{}_{}_{}", filter_id, start_block, end_block)
+        }
+        None => format!("logs_{}", filter_id),
+    }
+}
+
+// Blocks of a bounded job, read back from its name, none for the jobs following the tip.
+pub fn sync_job_range(job: &str) -> Option<(u32, u32)> {
+    let parts: Vec<&str> = job.split('_').collect();
+
+    let (start_block, end_block) = match parts.as_slice() {
+        ["range", start_block, end_block]
+        | ["logs", _, start_block, end_block] => (start_block, end_block),
+        _ => return None,
+    };
+
+    Some((start_block.parse().ok()?, end_block.parse().ok()?))
+}
+
+// Logs mode jobs store their rows without the blocks.
+pub fn is_logs_sync_job(job: &str) -> bool {
+    job.starts_with("logs_")
+}
+
+impl DatabaseInfoForSync {
+    pub fn new(chain: u64, job: &str, end_block: u32) -> Self {
+        Self {
+            chain,
+            end_block,
+            job: job.to_string(),
+            missing_blocks: Vec::new(),
+            timestamp: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounded_jobs_have_a_range() {
+        assert_eq!(
+            sync_job_range(&range_sync_job(100, 200)),
+            Some((100, 200))
+        );
+        assert_eq!(
+            sync_job_range(&logs_sync_job("0a1b2c3d", Some((100, 200)))),
+            Some((100, 200))
+        );
+    }
+
+    #[test]
+    fn tip_jobs_have_no_range() {
+        assert_eq!(sync_job_range(TIP_SYNC_JOB), None);
+        assert_eq!(sync_job_range(&logs_sync_job("0a1b2c3d", None)), None);
+    }
+
+    #[test]
+    fn logs_jobs_are_told_apart() {
+        assert!(is_logs_sync_job(&logs_sync_job("0a1b2c3d", None)));
+        assert!(!is_logs_sync_job(&range_sync_job(100, 200)));
+        assert!(!is_logs_sync_job(TIP_SYNC_JOB));
+    }
+}
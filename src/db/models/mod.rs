@@ -1,5 +1,8 @@
+pub mod authorization;
 pub mod block;
+pub mod block_audit;
 pub mod contract;
+pub mod decode_error;
 pub mod dex_trade;
 pub mod erc1155_transfer;
 pub mod erc20_transfer;
@@ -8,4 +11,6 @@ pub mod infoforsync;
 pub mod log;
 pub mod trace;
 pub mod transaction;
+pub mod uncle;
 pub mod withdrawal;
+pub mod withdrawal_summary;
@@ -1,14 +1,21 @@
 use std::ops::Mul;
 
 use clickhouse::Row;
-use ethers::types::{Transaction, TransactionReceipt};
-use primitive_types::{H160, U256};
+use ethers::types::{Transaction, TransactionReceipt, U64};
+use primitive_types::{H160, H256, U256};
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 use serde_with::serde_as;
 
-use crate::utils::format::{
-    byte4_from_input, format_address, format_bytes, format_hash, SerU256,
+use crate::{
+    chains::{
+        rewards::{get_blob_fee, get_priority_fee, get_receipt_blob_gas},
+        Rollup,
+    },
+    utils::format::{
+        byte4_from_input, format_address, format_bytes, format_hash,
+        SerU256,
+    },
 };
 
 #[derive(Debug, Clone, Serialize_repr, Deserialize_repr, PartialEq)]
@@ -17,6 +24,17 @@ pub enum TransactionType {
     Legacy = 0,
     AccessList = 1,
     Eip1559 = 2,
+    Blob = 3,
+    SetCode = 4,
+    ArbitrumDeposit = 100,
+    ArbitrumUnsigned = 101,
+    ArbitrumContract = 102,
+    ArbitrumRetry = 104,
+    ArbitrumSubmitRetryable = 105,
+    ArbitrumInternal = 106,
+    ArbitrumLegacy = 120,
+    // OP stack deposits, sent from L1 through the portal contract.
+    Deposit = 126,
 }
 
 #[serde_as]
@@ -35,6 +53,10 @@ type AccessList = Vec<(String, Vec<String>)>;
 pub struct DatabaseTransaction {
     pub access_list: AccessList,
     pub base_fee_per_gas: Option<u64>,
+    #[serde_as(as = "Option<SerU256>")]
+    pub blob_gas_price: Option<U256>,
+    pub blob_gas_used: Option<u32>,
+    pub blob_versioned_hashes: Vec<String>,
     pub block_hash: String,
     pub block_number: u32,
     #[serde_as(as = "Option<SerU256>")]
@@ -51,14 +73,30 @@ pub struct DatabaseTransaction {
     #[serde_as(as = "Option<SerU256>")]
     pub gas_price: Option<U256>,
     pub gas_used: Option<u32>,
+    pub gas_used_for_l1: Option<u32>,
     pub hash: String,
     pub input: String,
     #[serde_as(as = "Option<SerU256>")]
+    pub l1_blob_base_fee: Option<U256>,
+    pub l1_block_number: Option<u64>,
+    #[serde_as(as = "Option<SerU256>")]
+    pub l1_fee: Option<U256>,
+    #[serde_as(as = "Option<SerU256>")]
+    pub l1_gas_price: Option<U256>,
+    pub l1_gas_used: Option<u64>,
+    #[serde_as(as = "Option<SerU256>")]
+    pub max_fee_per_blob_gas: Option<U256>,
+    #[serde_as(as = "Option<SerU256>")]
     pub max_fee_per_gas: Option<U256>,
     #[serde_as(as = "Option<SerU256>")]
     pub max_priority_fee_per_gas: Option<U256>,
     pub method: String,
+    #[serde_as(as = "Option<SerU256>")]
+    pub mint: Option<U256>,
     pub nonce: u32,
+    #[serde_as(as = "Option<SerU256>")]
+    pub priority_fee: Option<U256>,
+    pub source_hash: Option<String>,
     pub status: Option<TransactionStatus>,
     pub timestamp: u32,
     pub to: String,
@@ -79,19 +117,25 @@ impl DatabaseTransaction {
             None => format_address(H160::zero()),
         };
 
-        let transaction_type: TransactionType =
-            match transaction.transaction_type {
-                Some(transaction_type) => {
-                    if transaction_type.as_usize() == 0 {
-                        TransactionType::AccessList
-                    } else if transaction_type.as_usize() == 1 {
-                        TransactionType::Eip1559
-                    } else {
-                        TransactionType::Legacy
-                    }
-                }
-                None => TransactionType::Legacy,
-            };
+        // Transactions from before EIP-2718 have no type, they are legacy ones.
+        let transaction_type: TransactionType = match transaction
+            .transaction_type
+            .map(|transaction_type| transaction_type.as_u64())
+        {
+            Some(1) => TransactionType::AccessList,
+            Some(2) => TransactionType::Eip1559,
+            Some(3) => TransactionType::Blob,
+            Some(4) => TransactionType::SetCode,
+            Some(100) => TransactionType::ArbitrumDeposit,
+            Some(101) => TransactionType::ArbitrumUnsigned,
+            Some(102) => TransactionType::ArbitrumContract,
+            Some(104) => TransactionType::ArbitrumRetry,
+            Some(105) => TransactionType::ArbitrumSubmitRetryable,
+            Some(106) => TransactionType::ArbitrumInternal,
+            Some(120) => TransactionType::ArbitrumLegacy,
+            Some(126) => TransactionType::Deposit,
+            _ => TransactionType::Legacy,
+        };
 
         let access_list: Vec<(String, Vec<String>)> =
             match transaction.access_list.to_owned() {
@@ -115,9 +159,39 @@ impl DatabaseTransaction {
                 None => Vec::new(),
             };
 
+        // Ethers leaves the blob fields of EIP-4844 transactions in the other fields.
+        let blob_versioned_hashes: Vec<String> = transaction
+            .other
+            .get_deserialized::<Vec<H256>>("blobVersionedHashes")
+            .and_then(Result::ok)
+            .unwrap_or_default()
+            .into_iter()
+            .map(format_hash)
+            .collect();
+
+        let max_fee_per_blob_gas: Option<U256> = transaction
+            .other
+            .get_deserialized("maxFeePerBlobGas")
+            .and_then(Result::ok);
+
+        // Deposits carry the hash identifying their L1 origin and the ETH minted on L2.
+        let mint: Option<U256> = transaction
+            .other
+            .get_deserialized("mint")
+            .and_then(Result::ok);
+
+        let source_hash: Option<String> = transaction
+            .other
+            .get_deserialized::<H256>("sourceHash")
+            .and_then(Result::ok)
+            .map(format_hash);
+
         Self {
             access_list,
             base_fee_per_gas: None,
+            blob_gas_price: None,
+            blob_gas_used: None,
+            blob_versioned_hashes,
             block_hash: format_hash(transaction.block_hash.unwrap()),
             block_number: transaction.block_number.unwrap().as_usize()
                 as u32,
@@ -131,8 +205,15 @@ impl DatabaseTransaction {
             gas: transaction.gas.as_usize() as u32,
             gas_price: transaction.gas_price,
             gas_used: None,
+            gas_used_for_l1: None,
             hash: format_hash(transaction.hash),
             input: format_bytes(&transaction.input),
+            l1_blob_base_fee: None,
+            l1_block_number: None,
+            l1_fee: None,
+            l1_gas_price: None,
+            l1_gas_used: None,
+            max_fee_per_blob_gas,
             max_fee_per_gas: transaction.max_fee_per_gas,
             max_priority_fee_per_gas: transaction.max_priority_fee_per_gas,
             method: format!(
@@ -141,7 +222,10 @@ impl DatabaseTransaction {
                     &transaction.input
                 )))
             ),
+            mint,
             nonce: transaction.nonce.as_usize() as u32,
+            priority_fee: None,
+            source_hash,
             status: None,
             timestamp,
             to,
@@ -159,12 +243,16 @@ impl DatabaseTransaction {
         base_fee_per_gas: Option<u64>,
         receipt: &TransactionReceipt,
     ) {
-        let gas_used = receipt.gas_used.unwrap();
+        let gas_used = receipt.gas_used.unwrap_or_default();
 
-        let effective_transaction_fee = receipt
-            .gas_used
-            .unwrap()
-            .mul(receipt.effective_gas_price.unwrap());
+        let blob_gas = get_receipt_blob_gas(receipt);
+
+        // The blob fee is paid on top of the execution fee and burned with the base fee.
+        let blob_fee = get_blob_fee(receipt);
+
+        let effective_transaction_fee = gas_used
+            .mul(receipt.effective_gas_price.unwrap_or_default())
+            .saturating_add(blob_fee);
 
         let status = match receipt.status {
             Some(status) => {
@@ -179,14 +267,22 @@ impl DatabaseTransaction {
             None => TransactionStatus::Unknown,
         };
 
+        // Deposits are paid for on L1, they burn no base fee.
         let burned = match base_fee_per_gas {
-            Some(base_fee_per_gas) => {
+            Some(base_fee_per_gas)
+                if self.transaction_type != TransactionType::Deposit =>
+            {
                 U256::from(base_fee_per_gas).mul(gas_used)
             }
-            None => U256::zero(),
-        };
+            _ => U256::zero(),
+        }
+        .saturating_add(blob_fee);
 
         self.base_fee_per_gas = base_fee_per_gas;
+        self.blob_gas_price =
+            blob_gas.map(|(blob_gas_price, _)| blob_gas_price);
+        self.blob_gas_used =
+            blob_gas.map(|(_, blob_gas_used)| blob_gas_used.as_u32());
         self.burned = Some(burned);
         self.contract_created =
             receipt.contract_address.map(format_address);
@@ -195,13 +291,65 @@ impl DatabaseTransaction {
         self.effective_gas_price = receipt.effective_gas_price;
         self.effective_transaction_fee = Some(effective_transaction_fee);
         self.gas_used = Some(gas_used.as_usize() as u32);
+        self.priority_fee =
+            Some(get_priority_fee(receipt, base_fee_per_gas));
         self.status = Some(status)
     }
 
+    // Receipt fields added by the rollup stack of the chain, ethers leaves them in the other
+    // fields. Must run after `add_receipt_data`.
+    pub fn add_rollup_receipt_data(
+        &mut self,
+        rollup: Rollup,
+        receipt: &TransactionReceipt,
+    ) {
+        let other = &receipt.other;
+
+        match rollup {
+            Rollup::OpStack => {
+                self.l1_blob_base_fee = other
+                    .get_deserialized("l1BlobBaseFee")
+                    .and_then(Result::ok);
+                self.l1_fee =
+                    other.get_deserialized("l1Fee").and_then(Result::ok);
+                self.l1_gas_price = other
+                    .get_deserialized("l1GasPrice")
+                    .and_then(Result::ok);
+                self.l1_gas_used = other
+                    .get_deserialized::<U64>("l1GasUsed")
+                    .and_then(Result::ok)
+                    .map(|l1_gas_used| l1_gas_used.as_u64());
+
+                // The L1 data fee is charged on top of the gas.
+                if let Some(l1_fee) = self.l1_fee {
+                    self.effective_transaction_fee = Some(
+                        self.effective_transaction_fee
+                            .unwrap_or_default()
+                            .saturating_add(l1_fee),
+                    );
+                }
+            }
+            Rollup::Arbitrum => {
+                // Already part of the gas used, so of the transaction fee.
+                self.gas_used_for_l1 = other
+                    .get_deserialized::<U64>("gasUsedForL1")
+                    .and_then(Result::ok)
+                    .map(|gas_used_for_l1| gas_used_for_l1.as_u32());
+                self.l1_block_number = other
+                    .get_deserialized::<U64>("l1BlockNumber")
+                    .and_then(Result::ok)
+                    .map(|l1_block_number| l1_block_number.as_u64());
+            }
+        }
+    }
+
     pub fn new() -> Self {
         Self {
             access_list: Vec::new(),
             base_fee_per_gas: None,
+            blob_gas_price: None,
+            blob_gas_used: None,
+            blob_versioned_hashes: Vec::new(),
             block_hash: "".to_string(),
             block_number: 0,
             burned: None,
@@ -214,12 +362,22 @@ impl DatabaseTransaction {
             gas: 0,
             gas_price: None,
             gas_used: None,
+            gas_used_for_l1: None,
             hash: "".to_string(),
             input: "".to_string(),
+            l1_blob_base_fee: None,
+            l1_block_number: None,
+            l1_fee: None,
+            l1_gas_price: None,
+            l1_gas_used: None,
+            max_fee_per_blob_gas: None,
             max_fee_per_gas: None,
             max_priority_fee_per_gas: None,
             method: "".to_string(),
+            mint: None,
             nonce: 0,
+            priority_fee: None,
+            source_hash: None,
             status: None,
             timestamp: 0,
             to: "".to_string(),
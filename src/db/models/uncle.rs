@@ -0,0 +1,46 @@
+use clickhouse::Row;
+use primitive_types::U256;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+
+use crate::{db::models::block::DatabaseBlock, utils::format::SerU256};
+
+// Uncle included by a block, the block is its nephew.
+#[serde_as]
+#[derive(Debug, Clone, Row, Serialize, Deserialize)]
+pub struct DatabaseUncle {
+    pub block_hash: String,
+    pub block_number: u32,
+    pub chain: u64,
+    pub hash: String,
+    pub inclusion_distance: u8,
+    pub miner: String,
+    pub number: u32,
+    #[serde_as(as = "SerU256")]
+    pub reward: U256,
+    pub timestamp: u32,
+    pub uncle_index: u8,
+}
+
+impl DatabaseUncle {
+    // Links `uncle`, whose reward is already added, to the block including it.
+    pub fn new(
+        nephew: &DatabaseBlock,
+        uncle: &DatabaseBlock,
+        uncle_index: u8,
+    ) -> Self {
+        Self {
+            block_hash: nephew.hash.clone(),
+            block_number: nephew.number,
+            chain: nephew.chain,
+            hash: uncle.hash.clone(),
+            inclusion_distance: nephew.number.saturating_sub(uncle.number)
+                as u8,
+            miner: uncle.miner.clone(),
+            number: uncle.number,
+            reward: uncle.base_block_reward,
+            timestamp: nephew.timestamp,
+            uncle_index,
+        }
+    }
+}
@@ -2,10 +2,22 @@ use clickhouse::Row;
 use ethers::types::Withdrawal;
 use primitive_types::U256;
 use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
 use serde_with::serde_as;
 
 use crate::utils::format::{format_address, SerU256};
 
+// Withdrawals of at least the ejection balance, 16 ETH in gwei, are taken as the full withdrawal
+// of an exited validator, smaller ones as the sweep of its balance above 32 ETH.
+const FULL_WITHDRAWAL_MIN_AMOUNT: u64 = 16_000_000_000;
+
+#[derive(Debug, Clone, Serialize_repr, Deserialize_repr, PartialEq)]
+#[repr(u8)]
+pub enum WithdrawalType {
+    Partial = 0,
+    Full = 1,
+}
+
 #[serde_as]
 #[derive(Debug, Clone, Row, Serialize, Deserialize)]
 pub struct DatabaseWithdrawal {
@@ -17,6 +29,7 @@ pub struct DatabaseWithdrawal {
     pub timestamp: u32,
     pub validator_index: u32,
     pub withdrawal_index: u32,
+    pub withdrawal_type: WithdrawalType,
 }
 
 impl DatabaseWithdrawal {
@@ -26,6 +39,15 @@ impl DatabaseWithdrawal {
         block_number: u32,
         timestamp: u32,
     ) -> Self {
+        // Amounts are returned by the nodes in gwei.
+        let withdrawal_type = if withdrawal.amount
+            >= U256::from(FULL_WITHDRAWAL_MIN_AMOUNT)
+        {
+            WithdrawalType::Full
+        } else {
+            WithdrawalType::Partial
+        };
+
         Self {
             address: format_address(withdrawal.address),
             amount: withdrawal.amount,
@@ -34,6 +56,39 @@ impl DatabaseWithdrawal {
             timestamp,
             validator_index: withdrawal.validator_index.as_usize() as u32,
             withdrawal_index: withdrawal.index.as_usize() as u32,
+            withdrawal_type,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn withdrawal_type(amount: u64) -> WithdrawalType {
+        let withdrawal = Withdrawal {
+            amount: U256::from(amount),
+            ..Default::default()
+        };
+
+        DatabaseWithdrawal::from_rpc(&withdrawal, 1, 0, 0).withdrawal_type
+    }
+
+    #[test]
+    fn sweeps_are_partial() {
+        assert_eq!(withdrawal_type(0), WithdrawalType::Partial);
+        assert_eq!(
+            withdrawal_type(FULL_WITHDRAWAL_MIN_AMOUNT - 1),
+            WithdrawalType::Partial
+        );
+    }
+
+    #[test]
+    fn withdrawals_from_the_ejection_balance_are_full() {
+        assert_eq!(
+            withdrawal_type(FULL_WITHDRAWAL_MIN_AMOUNT),
+            WithdrawalType::Full
+        );
+        assert_eq!(withdrawal_type(32_000_000_000), WithdrawalType::Full);
+    }
+}
@@ -0,0 +1,22 @@
+use clickhouse::Row;
+use primitive_types::U256;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+
+use crate::utils::format::SerU256;
+
+// Withdrawals of a validator or an address, aggregated from the stored withdrawals. The amounts
+// are in gwei, as stored.
+#[serde_as]
+#[derive(Debug, Clone, Default, Row, Serialize, Deserialize)]
+pub struct DatabaseWithdrawalSummary {
+    pub first_block: u32,
+    pub first_timestamp: u32,
+    pub full_withdrawals: u64,
+    pub last_block: u32,
+    pub last_timestamp: u32,
+    pub partial_withdrawals: u64,
+    #[serde_as(as = "SerU256")]
+    pub total_amount: U256,
+    pub withdrawals: u64,
+}
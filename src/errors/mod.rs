@@ -0,0 +1,58 @@
+use ethers::abi::ethabi;
+use std::fmt;
+
+// Failures of the fetch, decode and store paths. They are reported and recovered from (the block is
+// marked as missing, the log is recorded as undecodable, the store is retried) instead of stopping
+// the indexer.
+#[derive(Debug)]
+pub enum IndexerError {
+    // Log or transaction data that doesn't match the expected ABI.
+    Decode(String),
+    // Node request that failed or returned an unexpected response.
+    Rpc(String),
+    // Database request that failed.
+    Storage(clickhouse::error::Error),
+}
+
+impl fmt::Display for IndexerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IndexerError::Decode(err) => {
+                write!(f, "decode error: {}", err)
+            }
+            IndexerError::Rpc(err) => write!(f, "rpc error: {}", err),
+            IndexerError::Storage(err) => {
+                write!(f, "storage error: {}", err)
+            }
+        }
+    }
+}
+
+impl std::error::Error for IndexerError {}
+
+impl From<clickhouse::error::Error> for IndexerError {
+    fn from(err: clickhouse::error::Error) -> Self {
+        IndexerError::Storage(err)
+    }
+}
+
+impl From<ethabi::Error> for IndexerError {
+    fn from(err: ethabi::Error) -> Self {
+        IndexerError::Decode(err.to_string())
+    }
+}
+
+impl IndexerError {
+    // Storage failures that may succeed when retried, e.g. a dropped connection or an overloaded
+    // server.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            IndexerError::Storage(
+                clickhouse::error::Error::Network(_)
+                    | clickhouse::error::Error::BadResponse(_)
+                    | clickhouse::error::Error::TimedOut
+            )
+        )
+    }
+}
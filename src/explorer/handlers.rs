@@ -1,5 +1,9 @@
-use crate::{configs::Config, db::Database, explorer::models::*};
+use crate::{
+    configs::Config, db::Database, explorer::models::*,
+    utils::format::format_address,
+};
 use actix_web::{web, HttpResponse, Responder};
+use ethers::types::Address;
 use log::info;
 
 pub async fn index() -> impl Responder {
@@ -10,23 +14,88 @@ pub async fn status() -> impl Responder {
     HttpResponse::Ok().body("Status: Running")
 }
 
+// Databases of the indexed chains in the order of `--chains`, built once at startup and shared
+// by the handlers.
+pub struct ChainDatabases {
+    databases: Vec<Database>,
+}
+
+impl ChainDatabases {
+    pub async fn new(configs: &[Config]) -> Self {
+        let mut databases = Vec::new();
+
+        for config in configs.iter() {
+            databases.push(
+                Database::new(
+                    config.db_host.clone(),
+                    config.db_username.clone(),
+                    config.db_password.clone(),
+                    config.db_name.clone(),
+                    config.chain.clone(),
+                )
+                .await,
+            );
+        }
+
+        Self { databases }
+    }
+}
+
+// Database of the chain selected by the request, the first chain without one, or the response to
+// send when it isn't indexed.
+fn chain_database<'a>(
+    databases: &'a ChainDatabases,
+    chain: &ChainQuery,
+) -> Result<&'a Database, HttpResponse> {
+    let database = match chain.chain {
+        Some(id) => {
+            databases.databases.iter().find(|db| db.chain.id == id)
+        }
+        None => databases.databases.first(),
+    };
+
+    database.ok_or_else(|| {
+        HttpResponse::NotFound().body(format!(
+            "Chain {} is not indexed",
+            chain.chain.unwrap_or_default()
+        ))
+    })
+}
+
+// Address of a path in its stored format, or the response to send when it isn't one.
+fn parse_address(address: &str) -> Result<String, HttpResponse> {
+    match address.parse::<Address>() {
+        Ok(address) => Ok(format_address(address)),
+        Err(_) => Err(HttpResponse::BadRequest()
+            .body(format!("Invalid address {}", address))),
+    }
+}
+
+pub async fn handle_get_chains(
+    databases: web::Data<ChainDatabases>,
+) -> impl Responder {
+    let chains: Vec<ChainResponse> = databases
+        .databases
+        .iter()
+        .map(|db| ChainResponse::from(db.chain.clone()))
+        .collect();
+
+    HttpResponse::Ok().content_type("application/json").json(chains)
+}
+
 // Handler Functions
 pub async fn handle_get_blocks(
     query: web::Query<GetBlockQuery>,
+    chain: web::Query<ChainQuery>,
+    databases: web::Data<ChainDatabases>,
 ) -> impl Responder {
     info!("Here");
     let skip_count = query.items_count.unwrap_or(0);
 
-    let config = Config::new();
-
-    let db = Database::new(
-        config.db_host.clone(),
-        config.db_username.clone(),
-        config.db_password.clone(),
-        config.db_name.clone(),
-        config.chain.clone(),
-    )
-    .await;
+    let db = match chain_database(&databases, &chain) {
+        Ok(db) => db,
+        Err(response) => return response,
+    };
 
     let database_blocks = db.get_blocks(skip_count, 50).await;
     println!("-------- db_blocks -------- {:?}", database_blocks);
@@ -53,22 +122,19 @@ pub async fn handle_get_blocks(
 // Define a handler function that accepts a web::Path wrapping a tuple containing the ID
 pub async fn handle_get_block_by_id(
     query: web::Path<(u64,)>,
+    chain: web::Query<ChainQuery>,
+    databases: web::Data<ChainDatabases>,
 ) -> impl Responder {
     let block_id = query.0;
     info!("You requested information for block ID: {}", block_id.clone());
-    let config = Config::new();
-
-    let db = Database::new(
-        config.db_host.clone(),
-        config.db_username.clone(),
-        config.db_password.clone(),
-        config.db_name.clone(),
-        config.chain.clone(),
-    )
-    .await;
-
-    let database_block =
+    let db = match chain_database(&databases, &chain) {
+        Ok(db) => db,
+        Err(response) => return response,
+    };
+
+    let mut database_block =
         BlockResponse::from(db.get_block_by_id(block_id.clone()).await);
+    database_block.add_uncle_rewards(&db.get_block_uncles(block_id).await);
     HttpResponse::Ok()
         .content_type("application/json")
         .json(database_block)
@@ -76,19 +142,15 @@ pub async fn handle_get_block_by_id(
 
 pub async fn handle_get_transactions(
     query: web::Query<GetTransactionQuery>,
+    chain: web::Query<ChainQuery>,
+    databases: web::Data<ChainDatabases>,
 ) -> impl Responder {
     let skip_count = query.items_count.unwrap_or(0);
 
-    let config = Config::new();
-
-    let db = Database::new(
-        config.db_host.clone(),
-        config.db_username.clone(),
-        config.db_password.clone(),
-        config.db_name.clone(),
-        config.chain.clone(),
-    )
-    .await;
+    let db = match chain_database(&databases, &chain) {
+        Ok(db) => db,
+        Err(response) => return response,
+    };
 
     let database_transactions = db.get_transactions(skip_count, 50).await;
     println!(
@@ -122,19 +184,15 @@ pub async fn handle_get_transactions(
 // Define a handler function that accepts a web::Path wrapping a tuple containing the ID
 pub async fn handle_get_transaction_by_id(
     query: web::Path<(String,)>,
+    chain: web::Query<ChainQuery>,
+    databases: web::Data<ChainDatabases>,
 ) -> impl Responder {
     let hash = query.into_inner().0;
     info!("You requested information for block ID: {}", hash.clone());
-    let config = Config::new();
-
-    let db = Database::new(
-        config.db_host.clone(),
-        config.db_username.clone(),
-        config.db_password.clone(),
-        config.db_name.clone(),
-        config.chain.clone(),
-    )
-    .await;
+    let db = match chain_database(&databases, &chain) {
+        Ok(db) => db,
+        Err(response) => return response,
+    };
 
     let database_transaction = TransactionResponse::from(
         db.get_transaction_by_id(hash.clone()).await,
@@ -145,6 +203,97 @@ pub async fn handle_get_transaction_by_id(
         .json(database_transaction)
 }
 
+pub async fn handle_get_address_delegation(
+    query: web::Path<(String,)>,
+    chain: web::Query<ChainQuery>,
+    databases: web::Data<ChainDatabases>,
+) -> impl Responder {
+    let address = query.into_inner().0;
+    info!("You requested the delegation of address: {}", address);
+    let address = match parse_address(&address) {
+        Ok(address) => address,
+        Err(response) => return response,
+    };
+    let db = match chain_database(&databases, &chain) {
+        Ok(db) => db,
+        Err(response) => return response,
+    };
+
+    let delegation = DelegationResponse::new(
+        address.clone(),
+        db.get_delegation(&address).await,
+    );
+
+    HttpResponse::Ok().content_type("application/json").json(delegation)
+}
+
+pub async fn handle_get_block_withdrawals(
+    query: web::Path<(u64,)>,
+    chain: web::Query<ChainQuery>,
+    databases: web::Data<ChainDatabases>,
+) -> impl Responder {
+    let block_id = query.0;
+    info!("You requested the withdrawals of block ID: {}", block_id);
+    let db = match chain_database(&databases, &chain) {
+        Ok(db) => db,
+        Err(response) => return response,
+    };
+
+    let withdrawals: Vec<WithdrawalResponse> = db
+        .get_block_withdrawals(block_id)
+        .await
+        .into_iter()
+        .map(WithdrawalResponse::from)
+        .collect();
+
+    HttpResponse::Ok().content_type("application/json").json(withdrawals)
+}
+
+pub async fn handle_get_validator_withdrawals(
+    query: web::Path<(u32,)>,
+    chain: web::Query<ChainQuery>,
+    databases: web::Data<ChainDatabases>,
+) -> impl Responder {
+    let validator_index = query.0;
+    info!(
+        "You requested the withdrawals of validator: {}",
+        validator_index
+    );
+    let db = match chain_database(&databases, &chain) {
+        Ok(db) => db,
+        Err(response) => return response,
+    };
+
+    let summary = WithdrawalSummaryResponse::from(
+        db.get_validator_withdrawal_summary(validator_index).await,
+    );
+
+    HttpResponse::Ok().content_type("application/json").json(summary)
+}
+
+pub async fn handle_get_address_withdrawals(
+    query: web::Path<(String,)>,
+    chain: web::Query<ChainQuery>,
+    databases: web::Data<ChainDatabases>,
+) -> impl Responder {
+    let address = query.into_inner().0;
+    info!("You requested the withdrawals of address: {}", address);
+    let address = match parse_address(&address) {
+        Ok(address) => address,
+        Err(response) => return response,
+    };
+    let db = match chain_database(&databases, &chain) {
+        Ok(db) => db,
+        Err(response) => return response,
+    };
+
+    let summary = WithdrawalSummaryResponse::from(
+        db.get_address_withdrawal_summary(&address).await,
+    );
+
+    HttpResponse::Ok().content_type("application/json").json(summary)
+}
+
 pub async fn handle_get_transaction_summary_for_id(
     query: web::Path<(String,)>,
 ) -> impl Responder {
@@ -177,18 +326,14 @@ pub async fn handle_get_indexing_status(
 }
 pub async fn handle_get_stats(
     query: web::Query<EmptyQuery>,
+    chain: web::Query<ChainQuery>,
+    databases: web::Data<ChainDatabases>,
 ) -> impl Responder {
     info!("-------------------- You are trying to get stats --------------------");
-    let config = Config::new();
-
-    let db = Database::new(
-        config.db_host.clone(),
-        config.db_username.clone(),
-        config.db_password.clone(),
-        config.db_name.clone(),
-        config.chain.clone(),
-    )
-    .await;
+    let db = match chain_database(&databases, &chain) {
+        Ok(db) => db,
+        Err(response) => return response,
+    };
 
     let mut stats_response = StatsResponse::new();
     let info_for_average_block = db.get_info_for_average_block().await;
@@ -264,18 +409,14 @@ pub async fn handle_get_stats(
 
 pub async fn handle_main_page_blocks(
     query: web::Query<EmptyQuery>,
+    chain: web::Query<ChainQuery>,
+    databases: web::Data<ChainDatabases>,
 ) -> impl Responder {
-    let config = Config::new();
-
     info!(" ((((((((((( We are here!!! )))))))))))");
-    let db = Database::new(
-        config.db_host.clone(),
-        config.db_username.clone(),
-        config.db_password.clone(),
-        config.db_name.clone(),
-        config.chain.clone(),
-    )
-    .await;
+    let db = match chain_database(&databases, &chain) {
+        Ok(db) => db,
+        Err(response) => return response,
+    };
 
     let database_blocks = db.get_blocks(0, 6).await;
     info!("-------- main_page db_blocks -------- {:?}", database_blocks);
@@ -287,17 +428,13 @@ pub async fn handle_main_page_blocks(
 
 pub async fn handle_main_page_transactions(
     query: web::Query<EmptyQuery>,
+    chain: web::Query<ChainQuery>,
+    databases: web::Data<ChainDatabases>,
 ) -> impl Responder {
-    let config = Config::new();
-
-    let db = Database::new(
-        config.db_host.clone(),
-        config.db_username.clone(),
-        config.db_password.clone(),
-        config.db_name.clone(),
-        config.chain.clone(),
-    )
-    .await;
+    let db = match chain_database(&databases, &chain) {
+        Ok(db) => db,
+        Err(response) => return response,
+    };
 
     let database_transactions = db.get_transactions(0, 6).await;
     let transactions: Vec<TransactionResponse> = database_transactions
@@ -314,18 +451,14 @@ pub async fn handle_main_page_transactions(
 
 pub async fn handle_get_stats_charts_transactions(
     query: web::Query<EmptyQuery>,
+    chain: web::Query<ChainQuery>,
+    databases: web::Data<ChainDatabases>,
 ) -> impl Responder {
     info!("-------------------- You are trying to get stats charts transactions --------------------");
-    let config = Config::new();
-
-    let db = Database::new(
-        config.db_host.clone(),
-        config.db_username.clone(),
-        config.db_password.clone(),
-        config.db_name.clone(),
-        config.chain.clone(),
-    )
-    .await;
+    let db = match chain_database(&databases, &chain) {
+        Ok(db) => db,
+        Err(response) => return response,
+    };
 
     let chart_transaction = db.get_chart_transaction_data().await;
     let chart_transaction_data =
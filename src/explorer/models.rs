@@ -1,6 +1,13 @@
-use crate::db::models::{
-    block::DatabaseBlock,
-    transaction::{DatabaseTransaction, TransactionStatus},
+use crate::{
+    chains::Chain,
+    db::models::{
+        authorization::DatabaseAuthorization,
+        block::DatabaseBlock,
+        transaction::{DatabaseTransaction, TransactionStatus},
+        uncle::DatabaseUncle,
+        withdrawal::{DatabaseWithdrawal, WithdrawalType},
+        withdrawal_summary::DatabaseWithdrawalSummary,
+    },
 };
 
 use chrono::{TimeZone, Utc};
@@ -19,6 +26,7 @@ pub struct Miner {
 #[derive(Deserialize, Serialize, Clone)]
 pub struct Reward {
     pub reward: String,
+    #[serde(rename = "type")]
     pub type_field: String, // "type" is a reserved keyword in Rust, hence "type_field"
 }
 
@@ -40,7 +48,7 @@ pub struct BlockResponse {
     pub miner: Miner,
     pub nonce: String,
     pub parent_hash: String,
-    pub priority_fee: u64,
+    pub priority_fee: String,
     pub rewards: Vec<Reward>,
     pub size: u64,
     pub timestamp: String,
@@ -54,17 +62,39 @@ pub struct BlockResponse {
 
 impl From<DatabaseBlock> for BlockResponse {
     fn from(db_block: DatabaseBlock) -> Self {
+        // The producer earns the issuance, the priority fees and the reward for including uncles.
+        let mut rewards = vec![Reward {
+            reward: (db_block.base_block_reward
+                + db_block.uncle_rewards
+                + db_block.total_fee_reward)
+                .to_string(),
+            type_field: String::from("Miner Reward"),
+        }];
+
+        if !db_block.uncles.is_empty() {
+            rewards.push(Reward {
+                reward: db_block.uncle_rewards.to_string(),
+                type_field: String::from("Uncle Inclusion Reward"),
+            });
+        }
+
         BlockResponse {
             base_fee_per_gas: db_block
                 .base_fee_per_gas
                 .map(|v| v.to_string())
                 .unwrap_or_default(),
-            blob_gas_used: db_block.gas_used.to_string(),
-            blob_tx_count: db_block.transactions as u32,
+            blob_gas_used: db_block
+                .blob_gas_used
+                .map(|v| v.to_string())
+                .unwrap_or_default(),
+            blob_tx_count: db_block.blob_transactions as u32,
             burnt_fees: db_block.burned.to_string(),
             burnt_fees_percentage: 0.0, // Calculate or provide a default
             difficulty: db_block.difficulty.to_string(),
-            excess_blob_gas: "0".to_string(), // Default if not available
+            excess_blob_gas: db_block
+                .excess_blob_gas
+                .map(|v| v.to_string())
+                .unwrap_or_default(),
             gas_limit: db_block.gas_limit.to_string(),
             gas_target_percentage: 0.0, // Calculate or provide a default
             gas_used: db_block.gas_used.to_string(),
@@ -78,8 +108,8 @@ impl From<DatabaseBlock> for BlockResponse {
             },
             nonce: db_block.nonce,
             parent_hash: db_block.parent_hash,
-            priority_fee: 0, // Default if not available
-            rewards: vec![], // Construct Reward vector as necessary
+            priority_fee: db_block.total_fee_reward.to_string(),
+            rewards,
             size: db_block.size as u64,
             timestamp: Utc
                 .timestamp(db_block.timestamp as i64, 0)
@@ -90,10 +120,23 @@ impl From<DatabaseBlock> for BlockResponse {
                 .map(|v| v.to_string())
                 .unwrap_or_default(),
             tx_count: db_block.transactions as u32,
-            tx_fees: db_block.total_fee_reward.to_string(),
+            tx_fees: (db_block.total_fee_reward + db_block.burned)
+                .to_string(),
             r#type: "block".to_string(),
             uncles_hashes: db_block.uncles,
-            withdrawals_count: None, // Default or convert if possible
+            withdrawals_count: db_block.withdrawals.map(|v| v as u32),
+        }
+    }
+}
+
+impl BlockResponse {
+    // Rewards of the producers of the uncles included by the block, in the order of the uncles.
+    pub fn add_uncle_rewards(&mut self, uncles: &[DatabaseUncle]) {
+        for uncle in uncles {
+            self.rewards.push(Reward {
+                reward: uncle.reward.to_string(),
+                type_field: String::from("Uncle Reward"),
+            });
         }
     }
 }
@@ -203,7 +246,7 @@ impl From<DatabaseTransaction> for TransactionResponse {
             method: Some(dt.method.clone()),
             nonce: dt.nonce,
             position: dt.transaction_index as u32,
-            priority_fee: None,
+            priority_fee: dt.priority_fee.map(|v| v.to_string()),
             raw_input: dt.input,
             result: "success".to_string(),
             revert_reason: None,
@@ -343,6 +386,139 @@ impl StatsResponse {
     }
 }
 
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct DelegationResponse {
+    pub address: String,
+    pub block: Option<u64>,
+    pub delegated_to: Option<String>,
+    pub is_delegated: bool,
+    pub transaction_hash: Option<String>,
+}
+
+impl DelegationResponse {
+    // Accounts without authorizations, or whose latest one is a revocation, are not delegated.
+    pub fn new(
+        address: String,
+        authorization: Option<DatabaseAuthorization>,
+    ) -> Self {
+        match authorization {
+            Some(authorization) => DelegationResponse {
+                address,
+                block: Some(authorization.block_number as u64),
+                delegated_to: if authorization.is_revocation() {
+                    None
+                } else {
+                    Some(authorization.address.clone())
+                },
+                is_delegated: !authorization.is_revocation(),
+                transaction_hash: Some(authorization.transaction_hash),
+            },
+            None => DelegationResponse {
+                address,
+                block: None,
+                delegated_to: None,
+                is_delegated: false,
+                transaction_hash: None,
+            },
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct ChainResponse {
+    pub id: u64,
+    pub name: String,
+    pub native_decimals: u8,
+    pub native_symbol: String,
+}
+
+impl From<Chain> for ChainResponse {
+    fn from(chain: Chain) -> Self {
+        ChainResponse {
+            id: chain.id,
+            name: chain.name,
+            native_decimals: chain.native_decimals,
+            native_symbol: chain.native_symbol,
+        }
+    }
+}
+
+// Amounts of the withdrawals are in gwei, as returned by the nodes.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct WithdrawalResponse {
+    pub amount: String,
+    pub block_number: u64,
+    pub index: u32,
+    pub receiver: String,
+    pub timestamp: String,
+    pub r#type: String,
+    pub validator_index: u32,
+}
+
+impl From<DatabaseWithdrawal> for WithdrawalResponse {
+    fn from(dw: DatabaseWithdrawal) -> Self {
+        WithdrawalResponse {
+            amount: dw.amount.to_string(),
+            block_number: dw.block_number as u64,
+            index: dw.withdrawal_index,
+            receiver: dw.address,
+            timestamp: Utc
+                .timestamp_opt(dw.timestamp as i64, 0)
+                .single()
+                .map(|timestamp| {
+                    timestamp.format("%Y-%m-%dT%H:%M:%S%.fZ").to_string()
+                })
+                .unwrap_or_default(),
+            r#type: match dw.withdrawal_type {
+                WithdrawalType::Full => String::from("full"),
+                WithdrawalType::Partial => String::from("partial"),
+            },
+            validator_index: dw.validator_index,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct WithdrawalSummaryResponse {
+    pub first_block: Option<u64>,
+    pub first_timestamp: Option<String>,
+    pub full_withdrawals: u64,
+    pub last_block: Option<u64>,
+    pub last_timestamp: Option<String>,
+    pub partial_withdrawals: u64,
+    pub total_amount: String,
+    pub withdrawals: u64,
+}
+
+impl From<DatabaseWithdrawalSummary> for WithdrawalSummaryResponse {
+    fn from(summary: DatabaseWithdrawalSummary) -> Self {
+        let format_timestamp = |timestamp: u32| {
+            Utc.timestamp_opt(timestamp as i64, 0)
+                .single()
+                .map(|timestamp| {
+                    timestamp.format("%Y-%m-%dT%H:%M:%S%.fZ").to_string()
+                })
+                .unwrap_or_default()
+        };
+
+        // Without withdrawals the first and last values are empty instead of zero.
+        let seen = summary.withdrawals > 0;
+
+        WithdrawalSummaryResponse {
+            first_block: seen.then_some(summary.first_block as u64),
+            first_timestamp: seen
+                .then(|| format_timestamp(summary.first_timestamp)),
+            full_withdrawals: summary.full_withdrawals,
+            last_block: seen.then_some(summary.last_block as u64),
+            last_timestamp: seen
+                .then(|| format_timestamp(summary.last_timestamp)),
+            partial_withdrawals: summary.partial_withdrawals,
+            total_amount: summary.total_amount.to_string(),
+            withdrawals: summary.withdrawals,
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct NextPageParams {
     pub block_number: u64,
@@ -446,6 +622,12 @@ pub struct GetTransactionQuery {
 #[derive(Deserialize)]
 pub struct EmptyQuery {}
 
+// Chain selected by the `chain` parameter of any request, the first indexed chain by default.
+#[derive(Deserialize)]
+pub struct ChainQuery {
+    pub chain: Option<u64>,
+}
+
 #[derive(Deserialize)]
 pub struct AccountQuery {
     pub address: String,
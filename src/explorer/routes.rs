@@ -10,6 +10,10 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
                 "/api/v2/blocks/{id}",
                 web::get().to(handle_get_block_by_id),
             )
+            .route(
+                "/api/v2/blocks/{id}/withdrawals",
+                web::get().to(handle_get_block_withdrawals),
+            )
             .route(
                 "/api/v2/transactions",
                 web::get().to(handle_get_transactions),
@@ -22,6 +26,19 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
                 "/api/v2/transactions/{id}/summary",
                 web::get().to(handle_get_transaction_summary_for_id),
             )
+            .route(
+                "/api/v2/addresses/{address}/delegation",
+                web::get().to(handle_get_address_delegation),
+            )
+            .route(
+                "/api/v2/addresses/{address}/withdrawals",
+                web::get().to(handle_get_address_withdrawals),
+            )
+            .route(
+                "/api/v2/validators/{index}/withdrawals",
+                web::get().to(handle_get_validator_withdrawals),
+            )
+            .route("/api/v2/chains", web::get().to(handle_get_chains))
             .route("/api/v2/stats", web::get().to(handle_get_stats))
             .route(
                 "/api/v2/main-page/blocks",
@@ -2,41 +2,98 @@ mod bsc;
 mod ethereum;
 mod polygon;
 
-use std::collections::HashMap;
+use std::{collections::HashMap, fs};
 
 use primitive_types::{H160, U256};
 use serde::{Deserialize, Serialize};
 
 use crate::{
     chains::Chain,
-    db::models::transaction::{
-        DatabaseTransaction, TransactionStatus, TransactionType,
+    db::{
+        models::{
+            contract::DatabaseContract,
+            transaction::{
+                DatabaseTransaction, TransactionStatus, TransactionType,
+            },
+        },
+        BlockFetchedData,
     },
-    utils::format::format_address,
+    utils::format::{deserialize_u256_str, format_address},
 };
 
+// Account of the `alloc` section of a genesis, only the balance and code are indexed.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct BalanceAllocation {
+    #[serde(default, deserialize_with = "deserialize_u256_str")]
     balance: U256,
+    #[serde(default)]
+    code: Option<String>,
 }
 
-pub fn get_genesis_allocations(chain: Chain) -> Vec<DatabaseTransaction> {
+// Standard Geth genesis, e.g. the `genesis.json` used to initialize the nodes of a devnet.
+#[derive(Deserialize, Debug)]
+struct GenesisFile {
+    alloc: HashMap<String, BalanceAllocation>,
+}
+
+// Reads the `alloc` section of a genesis file, with addresses normalized to the indexed format.
+fn load_genesis_allocation(
+    path: &str,
+) -> Result<HashMap<String, BalanceAllocation>, String> {
+    let content = fs::read_to_string(path).map_err(|err| {
+        format!("unable to read genesis file {}: {}", path, err)
+    })?;
+
+    let genesis: GenesisFile =
+        serde_json::from_str(&content).map_err(|err| {
+            format!("unable to parse genesis file {}: {}", path, err)
+        })?;
+
+    Ok(genesis
+        .alloc
+        .into_iter()
+        .map(|(address, allocation)| {
+            let address = address.to_lowercase();
+            let address = match address.strip_prefix("0x") {
+                Some(_) => address,
+                None => format!("0x{}", address),
+            };
+
+            (address, allocation)
+        })
+        .collect())
+}
+
+// Genesis balances as transactions from the zero address, and the accounts deployed with code
+// as contracts, read from the genesis file of the chain or the built-in allocations.
+pub fn get_genesis_allocations(
+    chain: &Chain,
+) -> Result<BlockFetchedData, String> {
     let mut transactions = Vec::new();
+    let mut contracts = Vec::new();
 
-    let mut allocations = HashMap::new();
+    let allocations = match &chain.genesis_file {
+        Some(path) => load_genesis_allocation(path)?,
+        None => match chain.id {
+            1 => ethereum::get_genesis_allocation(),
+            56 => bsc::get_genesis_allocation(),
+            137 => polygon::get_genesis_allocation(),
+            _ => HashMap::new(),
+        },
+    };
 
-    if chain.id == 1 {
-        allocations = ethereum::get_genesis_allocation();
-    } else if chain.id == 56 {
-        allocations = bsc::get_genesis_allocation();
-    } else if chain.id == 137 {
-        allocations = polygon::get_genesis_allocation();
-    }
+    // Sorted so every run gives the same hashes to the same accounts.
+    let mut allocations: Vec<(String, BalanceAllocation)> =
+        allocations.into_iter().collect();
+    allocations.sort_by(|(a, _), (b, _)| a.cmp(b));
 
     for (i, (receiver, balance)) in allocations.iter().enumerate() {
         let transaction = DatabaseTransaction {
             access_list: Vec::new(),
             base_fee_per_gas: None,
+            blob_gas_price: None,
+            blob_gas_used: None,
+            blob_versioned_hashes: Vec::new(),
             block_hash: chain.genesis_hash.to_owned(),
             block_number: 0,
             burned: None,
@@ -49,12 +106,22 @@ pub fn get_genesis_allocations(chain: Chain) -> Vec<DatabaseTransaction> {
             gas: 0,
             gas_price: None,
             gas_used: None,
+            gas_used_for_l1: None,
             hash: format!("{}_GENESIS_{}", chain.name.to_uppercase(), i),
             input: String::from("0x"),
+            l1_blob_base_fee: None,
+            l1_block_number: None,
+            l1_fee: None,
+            l1_gas_price: None,
+            l1_gas_used: None,
+            max_fee_per_blob_gas: None,
             max_fee_per_gas: None,
             max_priority_fee_per_gas: None,
             method: String::from("0x000000"),
+            mint: None,
             nonce: 0,
+            priority_fee: None,
+            source_hash: None,
             timestamp: chain.genesis_timestamp,
             status: Some(TransactionStatus::Success),
             to: receiver.to_string(),
@@ -63,8 +130,18 @@ pub fn get_genesis_allocations(chain: Chain) -> Vec<DatabaseTransaction> {
             value: balance.balance,
         };
 
+        if balance.code.as_deref().is_some_and(|code| code != "0x") {
+            contracts.push(DatabaseContract {
+                block_number: 0,
+                chain: chain.id,
+                contract_address: receiver.to_string(),
+                creator: format_address(H160::zero()),
+                transaction_hash: transaction.hash.clone(),
+            });
+        }
+
         transactions.push(transaction);
     }
 
-    transactions
+    Ok(BlockFetchedData { contracts, transactions, ..Default::default() })
 }
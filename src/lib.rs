@@ -1,6 +1,7 @@
 pub mod chains;
 pub mod configs;
 pub mod db;
+pub mod errors;
 pub mod explorer;
 pub mod genesis;
 pub mod rpc;
@@ -0,0 +1,208 @@
+use log::info;
+use std::time::Duration;
+
+// Rows stored together in a single batch the sizer aims for.
+const TARGET_BATCH_ROWS: usize = 200_000;
+
+// Average time to fetch a block above which the rpcs are considered overloaded.
+const SLOW_FETCH_LATENCY: Duration = Duration::from_secs(5);
+
+// Time to store a batch above which the database is considered overloaded.
+const SLOW_STORE_LATENCY: Duration = Duration::from_secs(30);
+
+// Share of failed blocks in a batch above which the rpcs are considered overloaded.
+const HIGH_ERROR_RATE: f64 = 0.1;
+
+// Observations of a committed batch.
+pub struct BatchStats {
+    pub blocks: usize,
+    pub bytes: usize,
+    pub fetch_latency: Duration,
+    pub missing_blocks: usize,
+    pub rows: usize,
+    pub store_latency: Duration,
+}
+
+// Amount of blocks per backfill batch, adapted after every batch so that batches of heavy blocks
+// stay within the row and memory targets and batches of empty blocks grow instead of storing a
+// handful of rows per insert. Overloaded rpcs or an overloaded database shrink the batch
+// regardless of its weight.
+pub struct BatchSizer {
+    batch_size: usize,
+    max_batch_size: usize,
+    min_batch_size: usize,
+    target_bytes: usize,
+}
+
+impl BatchSizer {
+    pub fn new(
+        batch_size: usize,
+        min_batch_size: usize,
+        max_batch_size: usize,
+        target_bytes: usize,
+    ) -> Self {
+        let min_batch_size = min_batch_size.max(1);
+        let max_batch_size = max_batch_size.max(min_batch_size);
+
+        Self {
+            batch_size: batch_size.clamp(min_batch_size, max_batch_size),
+            max_batch_size,
+            min_batch_size,
+            target_bytes,
+        }
+    }
+
+    pub fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    pub fn observe(&mut self, stats: &BatchStats) {
+        if stats.blocks == 0 {
+            return;
+        }
+
+        let blocks = stats.blocks as f64;
+        let error_rate = stats.missing_blocks as f64 / blocks;
+        let fetch_latency = stats.fetch_latency.div_f64(blocks);
+        let rows_per_block = (stats.rows as f64 / blocks).max(1.0);
+        let bytes_per_block = (stats.bytes as f64 / blocks).max(1.0);
+
+        let (next_batch_size, reason) = if error_rate > HIGH_ERROR_RATE {
+            (self.batch_size / 2, "high error rate")
+        } else if fetch_latency > SLOW_FETCH_LATENCY {
+            (self.batch_size * 3 / 4, "slow fetches")
+        } else if stats.store_latency > SLOW_STORE_LATENCY {
+            (self.batch_size * 3 / 4, "slow stores")
+        } else {
+            // Size that fits the weight of the last blocks, reached at most doubling per batch.
+            let by_rows = TARGET_BATCH_ROWS as f64 / rows_per_block;
+            let by_bytes = self.target_bytes as f64 / bytes_per_block;
+            let fitting = by_rows.min(by_bytes) as usize;
+
+            if fitting < self.batch_size {
+                (fitting, "heavy blocks")
+            } else {
+                (fitting.min(self.batch_size * 2), "light blocks")
+            }
+        };
+
+        let next_batch_size = next_batch_size
+            .clamp(self.min_batch_size, self.max_batch_size);
+
+        if next_batch_size != self.batch_size {
+            info!(
+                "Batch size {} -> {} ({}): {:.0} rows/block, {:.0} KiB/block, fetch {:?}/block, {:.1}% missing, stored in {:?}.",
+                self.batch_size,
+                next_batch_size,
+                reason,
+                rows_per_block,
+                bytes_per_block / 1024.0,
+                fetch_latency,
+                error_rate * 100.0,
+                stats.store_latency
+            );
+
+            self.batch_size = next_batch_size;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGET_BYTES: usize = 1024 * 1024 * 1024;
+
+    fn stats(blocks: usize) -> BatchStats {
+        BatchStats {
+            blocks,
+            bytes: blocks * 1024,
+            fetch_latency: Duration::from_millis(100) * blocks as u32,
+            missing_blocks: 0,
+            rows: blocks * 10,
+            store_latency: Duration::from_secs(1),
+        }
+    }
+
+    #[test]
+    fn grows_on_light_blocks() {
+        let mut sizer = BatchSizer::new(100, 1, 10_000, TARGET_BYTES);
+
+        sizer.observe(&stats(100));
+
+        assert_eq!(sizer.batch_size(), 200);
+    }
+
+    #[test]
+    fn shrinks_on_heavy_blocks() {
+        let mut sizer = BatchSizer::new(100, 1, 10_000, TARGET_BYTES);
+
+        sizer.observe(&BatchStats { rows: 100 * 4_000, ..stats(100) });
+
+        assert_eq!(sizer.batch_size(), 50);
+    }
+
+    #[test]
+    fn shrinks_on_high_error_rate() {
+        let mut sizer = BatchSizer::new(100, 1, 10_000, TARGET_BYTES);
+
+        sizer.observe(&BatchStats { missing_blocks: 20, ..stats(100) });
+
+        assert_eq!(sizer.batch_size(), 50);
+    }
+
+    #[test]
+    fn shrinks_on_slow_fetches() {
+        let mut sizer = BatchSizer::new(100, 1, 10_000, TARGET_BYTES);
+
+        sizer.observe(&BatchStats {
+            fetch_latency: SLOW_FETCH_LATENCY * 2 * 100,
+            ..stats(100)
+        });
+
+        assert_eq!(sizer.batch_size(), 75);
+    }
+
+    #[test]
+    fn shrinks_on_slow_stores() {
+        let mut sizer = BatchSizer::new(100, 1, 10_000, TARGET_BYTES);
+
+        sizer.observe(&BatchStats {
+            store_latency: SLOW_STORE_LATENCY * 2,
+            ..stats(100)
+        });
+
+        assert_eq!(sizer.batch_size(), 75);
+    }
+
+    #[test]
+    fn clamps_to_max_batch_size() {
+        let mut sizer = BatchSizer::new(100, 1, 150, TARGET_BYTES);
+
+        sizer.observe(&stats(100));
+
+        assert_eq!(sizer.batch_size(), 150);
+    }
+
+    #[test]
+    fn clamps_to_min_batch_size() {
+        let mut sizer = BatchSizer::new(100, 80, 10_000, TARGET_BYTES);
+
+        sizer.observe(&BatchStats { missing_blocks: 50, ..stats(100) });
+
+        assert_eq!(sizer.batch_size(), 80);
+    }
+
+    #[test]
+    fn clamps_initial_batch_size() {
+        assert_eq!(
+            BatchSizer::new(5, 10, 100, TARGET_BYTES).batch_size(),
+            10
+        );
+        assert_eq!(
+            BatchSizer::new(500, 10, 100, TARGET_BYTES).batch_size(),
+            100
+        );
+        assert_eq!(BatchSizer::new(0, 0, 0, TARGET_BYTES).batch_size(), 1);
+    }
+}
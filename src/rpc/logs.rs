@@ -0,0 +1,329 @@
+use super::Rpc;
+use crate::{
+    configs::{Config, Dataset, LogFilter},
+    db::{models::log::DatabaseLog, BlockFetchedData, Database},
+    errors::IndexerError,
+    utils::decode::decode_log_events,
+};
+use ethers::types::{Block, Log, TxHash};
+use futures::stream::{self, StreamExt};
+use jsonrpsee::core::{client::ClientT, rpc_params};
+use log::{error, info, warn};
+use serde_json::{json, Error};
+use std::{
+    collections::{BTreeSet, HashMap},
+    time::Duration,
+};
+use tokio::time::sleep;
+
+// Attempts of a logs range failing for another reason than its size before the job stops.
+const LOGS_ATTEMPTS: u32 = 5;
+
+// Bounds of the backoff between attempts of a failing logs range.
+const LOGS_RETRY_MIN_BACKOFF: Duration = Duration::from_millis(500);
+const LOGS_RETRY_MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+// Parts of the errors returned by the rpc providers when a range matches too many logs.
+const TOO_MANY_RESULTS_ERRORS: [&str; 8] = [
+    "too many",
+    "more than",
+    "limit exceeded",
+    "exceeds the limit",
+    "response size",
+    "range is too large",
+    "block range",
+    "query timeout",
+];
+
+impl Rpc {
+    // Logs matching the filter from `from_block` to `to_block` (inclusive).
+    async fn get_logs(
+        &self,
+        filter: &LogFilter,
+        from_block: u32,
+        to_block: u32,
+    ) -> Result<Vec<Log>, IndexerError> {
+        let client = self.get_client();
+
+        let mut params = json!({
+            "fromBlock": format!("0x{:x}", from_block),
+            "toBlock": format!("0x{:x}", to_block),
+        });
+
+        if !filter.addresses.is_empty() {
+            params["address"] = json!(filter.addresses);
+        }
+
+        if !filter.topics.is_empty() {
+            params["topics"] = json!([filter.topics]);
+        }
+
+        let raw_logs =
+            client.request("eth_getLogs", rpc_params![params]).await;
+
+        match raw_logs {
+            Ok(value) => {
+                let logs: Result<Vec<Log>, Error> =
+                    serde_json::from_value(value);
+
+                logs.map_err(|err| {
+                    IndexerError::Rpc(format!(
+                        "unable to decode logs of blocks {} to {}: {}",
+                        from_block, to_block, err
+                    ))
+                })
+            }
+            Err(err) => Err(IndexerError::Rpc(format!(
+                "unable to get logs of blocks {} to {}: {}",
+                from_block, to_block, err
+            ))),
+        }
+    }
+
+    async fn get_block_timestamp(
+        &self,
+        block_number: &u32,
+    ) -> Result<u32, IndexerError> {
+        let client = self.get_client();
+
+        let raw_block = client
+            .request(
+                "eth_getBlockByNumber",
+                rpc_params![format!("0x{:x}", block_number), false],
+            )
+            .await;
+
+        match raw_block {
+            Ok(value) => {
+                let block: Result<Block<TxHash>, Error> =
+                    serde_json::from_value(value);
+
+                match block {
+                    Ok(block) => Ok(block.timestamp.as_u32()),
+                    Err(err) => Err(IndexerError::Rpc(format!(
+                        "unable to decode block {}: {}",
+                        block_number, err
+                    ))),
+                }
+            }
+            Err(err) => Err(IndexerError::Rpc(format!(
+                "unable to get block {}: {}",
+                block_number, err
+            ))),
+        }
+    }
+}
+
+fn is_too_many_results(err: &IndexerError) -> bool {
+    let message = err.to_string().to_lowercase();
+
+    TOO_MANY_RESULTS_ERRORS.iter().any(|pattern| message.contains(pattern))
+}
+
+// Indexes only the logs matching the configured filter, requesting them by block ranges through
+// eth_getLogs and deriving the transfers and dex trades from them.
+pub async fn sync_logs(rpc: &Rpc, db: &Database, config: &Config) {
+    let filter = match &config.log_filter {
+        Some(filter) => filter,
+        None => return,
+    };
+
+    info!("sync logs");
+
+    let job = config.sync_job.as_str();
+    let last_block = config.range_end_block();
+
+    let mut from_block = config.start_block;
+    if let Some(info_for_sync) = db.get_info_for_sync(job).await.first() {
+        from_block = from_block.max(info_for_sync.end_block);
+    }
+
+    info!(
+        "Indexing logs of {} addresses and {} topics for job {} from block {} to {}.",
+        filter.addresses.len(),
+        filter.topics.len(),
+        job,
+        from_block,
+        last_block.map_or("tip".to_string(), |block| block.to_string())
+    );
+
+    let mut range = config.log_range;
+    let mut attempts = 0;
+    let mut chain_head = 0;
+
+    while !rpc.shutdown.is_requested() {
+        if last_block.is_some_and(|last_block| from_block > last_block) {
+            break;
+        }
+
+        if from_block > chain_head {
+            chain_head = rpc.get_last_block().await;
+
+            if from_block > chain_head {
+                // Caught up with the chain, wait for new blocks before polling again.
+                tokio::select! {
+                    _ = sleep(rpc.poll_interval) => (),
+                    _ = rpc.shutdown.requested() => break,
+                }
+                continue;
+            }
+        }
+
+        let to_block = from_block
+            .saturating_add(range - 1)
+            .min(chain_head)
+            .min(last_block.unwrap_or(u32::MAX));
+
+        let fetched_data =
+            match rpc.get_logs(filter, from_block, to_block).await {
+                Ok(logs) => fetch_logs_data(rpc, config, logs).await,
+                Err(err) => Err(err),
+            };
+
+        let fetched_data = match fetched_data {
+            Ok(fetched_data) => fetched_data,
+            Err(err)
+                if to_block > from_block && is_too_many_results(&err) =>
+            {
+                // Split the range until the provider accepts it, it grows back after successes.
+                let blocks = to_block - from_block + 1;
+                range = (blocks / 2).max(1);
+                warn!(
+                    "{}, retrying with ranges of {} blocks.",
+                    err, range
+                );
+                continue;
+            }
+            Err(err) => {
+                attempts += 1;
+                if attempts >= LOGS_ATTEMPTS {
+                    error!(
+                        "{}, stopping job {} at block {}.",
+                        err, job, from_block
+                    );
+                    return;
+                }
+
+                let backoff = LOGS_RETRY_MIN_BACKOFF
+                    .saturating_mul(1 << attempts)
+                    .min(LOGS_RETRY_MAX_BACKOFF);
+                warn!("{}, retrying in {:?}.", err, backoff);
+                sleep(backoff).await;
+                continue;
+            }
+        };
+
+        attempts = 0;
+
+        let logs = fetched_data.logs.len();
+
+        if db.store_data(&fetched_data).await.is_err() {
+            error!(
+                "Unable to store logs of blocks {} to {}, stopping at the last committed range.",
+                from_block, to_block
+            );
+            return;
+        }
+
+        let end_block = to_block + 1;
+
+        if db
+            .update_info_for_sync(job, |info_for_sync| {
+                info_for_sync.end_block =
+                    info_for_sync.end_block.max(end_block);
+
+                true
+            })
+            .await
+            .is_err()
+        {
+            error!("Unable to update the checkpoint of job {}.", job);
+            return;
+        }
+
+        info!(
+            "Stored {} logs of blocks {} to {}.",
+            logs, from_block, to_block
+        );
+
+        from_block = end_block;
+        range = range.saturating_mul(2).min(config.log_range);
+    }
+
+    if rpc.shutdown.is_requested() {
+        info!("Job {} stopped, fetched logs committed.", job);
+    } else {
+        info!("Job {} finished.", job);
+    }
+}
+
+// Builds the rows of the matched logs. Their blocks are only requested for the timestamps, unless
+// they are fetched in full, which also indexes every other log of those blocks.
+async fn fetch_logs_data(
+    rpc: &Rpc,
+    config: &Config,
+    logs: Vec<Log>,
+) -> Result<BlockFetchedData, IndexerError> {
+    let block_numbers: BTreeSet<u32> = logs
+        .iter()
+        .filter_map(|log| log.block_number)
+        .map(|block_number| block_number.as_u32())
+        .collect();
+
+    if config.fetch_log_blocks {
+        let blocks: Vec<Result<BlockFetchedData, IndexerError>> =
+            stream::iter(block_numbers)
+                .map(|block_number| async move {
+                    rpc.fetch_block(&block_number, &config.chain).await
+                })
+                .buffer_unordered(config.fetch_concurrency)
+                .collect()
+                .await;
+
+        let mut fetched_data = BlockFetchedData::default();
+        for block in blocks {
+            fetched_data.append(&mut block?);
+        }
+
+        return Ok(fetched_data);
+    }
+
+    let timestamps: Vec<Result<(u32, u32), IndexerError>> =
+        stream::iter(block_numbers)
+            .map(|block_number| async move {
+                rpc.get_block_timestamp(&block_number)
+                    .await
+                    .map(|timestamp| (block_number, timestamp))
+            })
+            .buffer_unordered(config.fetch_concurrency)
+            .collect()
+            .await;
+
+    let timestamps: HashMap<u32, u32> =
+        timestamps.into_iter().collect::<Result<_, _>>()?;
+
+    let mut db_logs: Vec<DatabaseLog> = Vec::new();
+
+    for log in logs.iter() {
+        // Logs of pending blocks have no block number yet.
+        let block_number = match log.block_number {
+            Some(block_number) => block_number.as_u32(),
+            None => continue,
+        };
+
+        db_logs.push(DatabaseLog::from_rpc(
+            log,
+            config.chain.id,
+            timestamps[&block_number],
+            &block_number,
+        ));
+    }
+
+    let mut fetched_data = decode_log_events(&db_logs, &config.datasets);
+
+    if config.datasets.contains(&Dataset::Logs) {
+        fetched_data.logs = db_logs;
+    }
+
+    Ok(fetched_data)
+}
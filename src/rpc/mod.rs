@@ -1,41 +1,41 @@
+mod batch_sizer;
+mod logs;
+mod rederive;
+
+pub use self::{logs::sync_logs, rederive::rederive_chain};
+
+use self::batch_sizer::{BatchSizer, BatchStats};
 use crate::{
-    chains::{get_block_reward, Chain},
-    configs::Config,
+    chains::{
+        rewards::{check_reward_traces, BlockReward, BlockRewardModel},
+        Chain,
+    },
+    configs::{Config, Dataset},
     db::{
         models::{
+            authorization::DatabaseAuthorization,
             block::DatabaseBlock,
             contract::DatabaseContract,
-            dex_trade::DatabaseDexTrade,
-            erc1155_transfer::DatabaseERC1155Transfer,
-            erc20_transfer::DatabaseERC20Transfer,
-            erc721_transfer::DatabaseERC721Transfer,
-            infoforsync::DatabaseInfoForSync,
+            infoforsync::TIP_SYNC_JOB,
             log::DatabaseLog,
             trace::{DatabaseTrace, TraceType},
-            transaction::DatabaseTransaction,
+            transaction::{DatabaseTransaction, TransactionType},
+            uncle::DatabaseUncle,
             withdrawal::DatabaseWithdrawal,
         },
-        BlockFetchedData, Database, DatabaseTables,
+        BlockFetchedData, Database,
     },
+    errors::IndexerError,
     genesis::get_genesis_allocations,
     utils::{
-        events::{
-            ERC1155_TRANSFER_BATCH_EVENT_SIGNATURE,
-            ERC1155_TRANSFER_SINGLE_EVENT_SIGNATURE,
-            SWAPV3_EVENT_SIGNATURE, SWAP_EVENT_SIGNATURE,
-            TRANSFER_EVENTS_SIGNATURE,
-        },
-        format::{decode_bytes, format_hash},
+        decode::decode_log_events, format::format_hash, shutdown::Shutdown,
     },
 };
-use chrono::Utc;
-use ethabi::ParamType;
 use ethers::{
-    abi::ethabi,
     prelude::abigen,
     types::{Block, Trace, Transaction, TransactionReceipt, TxHash},
 };
-use futures::future::join_all;
+use futures::stream::{self, StreamExt};
 use primitive_types::U256;
 
 use jsonrpsee::{
@@ -50,10 +50,18 @@ use jsonrpsee_http_client::{
 };
 use jsonrpsee_ws_client::{WsClient, WsClientBuilder};
 
-use log::{info, warn};
+use log::{error, info, warn};
 use rand::seq::SliceRandom;
-use std::{collections::HashMap, ops::Mul, time::Duration};
-use tokio::time::sleep;
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Mul,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{
+    sync::{mpsc, OwnedSemaphorePermit, Semaphore},
+    time::{sleep, timeout, Instant},
+};
 
 use serde_json::Error;
 
@@ -68,16 +76,42 @@ abigen!(
         function token1() external view returns (address)
     ]"#,
 );
+
+// Amount of heads waiting to be fetched before the head listener is paused.
+const LIVE_QUEUE_CAPACITY: usize = 1024;
+
+// Amount of heads fetched in parallel by the live pipeline.
+const LIVE_FETCH_CONCURRENCY: usize = 4;
+
+// Time without new fetched blocks after which the backfill writer stores a partial batch.
+const BACKFILL_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+// Amount of blocks checked together by a single completeness query of the audit.
+const AUDIT_WINDOW: u32 = 10_000;
+
+// Bounds of the backoff between attempts to fetch a head that is not ready yet.
+const HEAD_RETRY_MIN_BACKOFF: Duration = Duration::from_millis(250);
+const HEAD_RETRY_MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+// Bounds of the exponential backoff used to reconnect the websocket.
+const WS_RECONNECT_MIN_BACKOFF: Duration = Duration::from_millis(500);
+const WS_RECONNECT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
 #[derive(Debug, Clone)]
 pub struct Rpc {
     pub chain: Chain,
     pub clients: Vec<HttpClient<HttpBackend>>,
     pub clients_urls: Vec<String>,
+    pub datasets: HashSet<Dataset>,
+    pub head_timeout: Duration,
+    pub poll_interval: Duration,
+    pub reward_model: Arc<dyn BlockRewardModel>,
+    pub shutdown: Shutdown,
     pub ws_url: Option<String>,
 }
 
 impl Rpc {
-    pub async fn new(config: &Config) -> Self {
+    pub async fn new(config: &Config, shutdown: Shutdown) -> Self {
         let timeout = Duration::from_secs(60);
 
         let mut clients = Vec::new();
@@ -120,6 +154,11 @@ impl Rpc {
             chain: config.chain.clone(),
             clients,
             clients_urls,
+            datasets: config.datasets.clone(),
+            head_timeout: Duration::from_secs(config.head_timeout),
+            poll_interval: Duration::from_millis(config.poll_interval),
+            reward_model: config.chain.block_reward_model(),
+            shutdown,
             ws_url: config.ws_url.clone(),
         }
     }
@@ -130,14 +169,14 @@ impl Rpc {
         let last_block =
             client.request("eth_blockNumber", rpc_params![]).await;
 
-        match last_block {
-            Ok(value) => {
-                let block_number: U256 = serde_json::from_value(value)
-                    .expect(
-                        "Unable to deserialize eth_blockNumber response",
-                    );
-
-                block_number.as_usize() as u32
+        match last_block.map(serde_json::from_value::<U256>) {
+            Ok(Ok(block_number)) => block_number.as_usize() as u32,
+            Ok(Err(err)) => {
+                warn!(
+                    "Unable to decode eth_blockNumber response: {}",
+                    err
+                );
+                0
             }
             Err(_) => 0,
         }
@@ -147,27 +186,22 @@ impl Rpc {
         &self,
         block_number: &u32,
         chain: &Chain,
-    ) -> Option<(
-        Vec<DatabaseBlock>,
-        Vec<DatabaseTransaction>,
-        Vec<DatabaseLog>,
-        Vec<DatabaseContract>,
-        Vec<DatabaseTrace>,
-        Vec<DatabaseWithdrawal>,
-        Vec<DatabaseERC20Transfer>,
-        Vec<DatabaseERC721Transfer>,
-        Vec<DatabaseERC1155Transfer>,
-        Vec<DatabaseDexTrade>,
-    )> {
+    ) -> Result<BlockFetchedData, IndexerError> {
         let block_data = self.get_block(block_number).await;
 
+        // Skip the calls of the datasets not indexed, e.g. on nodes without the trace api.
         let traces: Vec<DatabaseTrace> =
-            self.get_block_traces(block_number).await;
+            if self.datasets.contains(&Dataset::Traces) {
+                self.get_block_traces(block_number).await?
+            } else {
+                Vec::new()
+            };
 
         match block_data {
-            Some((
+            Ok((
                 mut db_block,
                 mut db_transactions,
+                db_authorizations,
                 db_withdrawals,
                 mut block_uncles,
             )) => {
@@ -176,15 +210,23 @@ impl Rpc {
                 // Make sure all the transactions are correctly formatted.
                 if db_block.transactions != total_block_transactions as u16
                 {
-                    warn!(
-                        "Missing {} transactions for block {}.",
+                    return Err(IndexerError::Rpc(format!(
+                        "missing {} transactions for block {}",
                         db_block.transactions
                             - total_block_transactions as u16,
                         db_block.number
-                    );
-                    return None;
+                    )));
                 }
 
+                db_block.blob_transactions = db_transactions
+                    .iter()
+                    .filter(|transaction| {
+                        transaction.transaction_type
+                            == TransactionType::Blob
+                    })
+                    .count()
+                    as u16;
+
                 let mut db_receipts: HashMap<String, TransactionReceipt> =
                     HashMap::new();
 
@@ -192,302 +234,198 @@ impl Rpc {
                 let mut contracts_map: HashMap<String, DatabaseContract> =
                     HashMap::new();
 
-                if chain.supports_blocks_receipts {
-                    let receipts_data = self
-                        .get_block_receipts(
-                            block_number,
-                            db_block.timestamp,
-                        )
-                        .await;
-
-                    match receipts_data {
-                        Some((receipts, mut logs, contracts)) => {
-                            for receipt in receipts {
-                                db_receipts.insert(
-                                    format_hash(receipt.transaction_hash),
-                                    receipt,
-                                );
-                            }
-                            db_logs.append(&mut logs);
-                            for contract in contracts {
-                                contracts_map.insert(
-                                    contract.contract_address.clone(),
-                                    contract.clone(),
-                                );
-                            }
-                        }
-                        None => return None,
-                    }
-                } else {
-                    for transaction in db_transactions.iter() {
-                        let receipt_data = self
-                            .get_transaction_receipt(
-                                transaction.hash.clone(),
-                                transaction.timestamp,
+                // Without receipts the transactions lack their receipt fields and the block its
+                // fee rewards.
+                if Dataset::needs_receipts(&self.datasets) {
+                    if chain.supports_blocks_receipts {
+                        let receipts_data = self
+                            .get_block_receipts(
                                 block_number,
+                                db_block.timestamp,
                             )
                             .await;
 
-                        match receipt_data {
-                            Some((receipt, mut logs, contract)) => {
-                                db_receipts.insert(
-                                    format_hash(receipt.transaction_hash),
-                                    receipt,
-                                );
+                        match receipts_data {
+                            Ok((receipts, mut logs, contracts)) => {
+                                for receipt in receipts {
+                                    db_receipts.insert(
+                                        format_hash(
+                                            receipt.transaction_hash,
+                                        ),
+                                        receipt,
+                                    );
+                                }
                                 db_logs.append(&mut logs);
-                                match contract {
-                                    Some(contract) => {
-                                        contracts_map.insert(
-                                            contract
-                                                .contract_address
-                                                .clone(),
-                                            contract.clone(),
-                                        );
+                                for contract in contracts {
+                                    contracts_map.insert(
+                                        contract.contract_address.clone(),
+                                        contract.clone(),
+                                    );
+                                }
+                            }
+                            Err(err) => return Err(err),
+                        }
+                    } else {
+                        for transaction in db_transactions.iter() {
+                            let receipt_data = self
+                                .get_transaction_receipt(
+                                    transaction.hash.clone(),
+                                    transaction.timestamp,
+                                    block_number,
+                                )
+                                .await;
+
+                            match receipt_data {
+                                Ok((receipt, mut logs, contract)) => {
+                                    db_receipts.insert(
+                                        format_hash(
+                                            receipt.transaction_hash,
+                                        ),
+                                        receipt,
+                                    );
+                                    db_logs.append(&mut logs);
+                                    match contract {
+                                        Some(contract) => {
+                                            contracts_map.insert(
+                                                contract
+                                                    .contract_address
+                                                    .clone(),
+                                                contract.clone(),
+                                            );
+                                        }
+                                        None => continue,
                                     }
-                                    None => continue,
+                                }
+                                Err(err) => {
+                                    warn!("{}", err);
+                                    continue;
                                 }
                             }
-                            None => continue,
                         }
                     }
-                }
 
-                if total_block_transactions != db_receipts.len() {
-                    warn!(
-                        "Missing receipts for block {}. Transactions {} receipts {}",
-                        db_block.number,
-                        total_block_transactions,
-                        db_receipts.len()
-                    );
-                    return None;
-                }
+                    if total_block_transactions != db_receipts.len() {
+                        return Err(IndexerError::Rpc(format!(
+                            "missing receipts for block {}, transactions {} receipts {}",
+                            db_block.number,
+                            total_block_transactions,
+                            db_receipts.len()
+                        )));
+                    }
 
-                // TODO: add receipt data to transactions
-                for transaction in db_transactions.iter_mut() {
-                    let receipt = db_receipts
-                        .get(&transaction.hash.clone())
-                        .expect("unable to get receipt for transaction");
+                    // TODO: add receipt data to transactions
+                    for transaction in db_transactions.iter_mut() {
+                        let receipt = match db_receipts
+                            .get_mut(&transaction.hash)
+                        {
+                            Some(receipt) => receipt,
+                            None => {
+                                return Err(IndexerError::Rpc(format!(
+                                    "missing receipt for transaction {}",
+                                    transaction.hash
+                                )))
+                            }
+                        };
 
-                    transaction.add_receipt_data(
-                        db_block.base_fee_per_gas,
-                        receipt,
-                    );
+                        // Nodes omit the effective gas price of legacy receipts, it is the gas price.
+                        if receipt.effective_gas_price.is_none() {
+                            receipt.effective_gas_price =
+                                transaction.gas_price;
+                        }
+
+                        transaction.add_receipt_data(
+                            db_block.base_fee_per_gas,
+                            receipt,
+                        );
+
+                        if let Some(rollup) = chain.rollup {
+                            transaction
+                                .add_rollup_receipt_data(rollup, receipt);
+                        }
+                    }
                 }
 
-                let (base_block_reward, total_fee_reward, uncle_rewards) =
-                    get_block_reward(
-                        self.chain.id,
-                        &db_block,
-                        Some(&db_receipts),
-                        &block_uncles,
-                        false,
-                        None,
-                    );
+                let block_reward = self.reward_model.block_reward(
+                    &db_block,
+                    Some(&db_receipts),
+                    &block_uncles,
+                );
 
-                let burned = match db_block.base_fee_per_gas {
-                    Some(base_fee_per_gas) => U256::from(base_fee_per_gas)
-                        .mul(U256::from(db_block.gas_used)),
-                    None => U256::zero(),
+                // With the receipts the burned fees of the transactions are summed, they add
+                // the blob fees and leave out the deposits, without them only the base fees are
+                // counted.
+                let burned = if db_receipts.is_empty() {
+                    match db_block.base_fee_per_gas {
+                        Some(base_fee_per_gas) => {
+                            U256::from(base_fee_per_gas)
+                                .mul(U256::from(db_block.gas_used))
+                        }
+                        None => U256::zero(),
+                    }
+                } else {
+                    let mut burned = U256::zero();
+                    for transaction in db_transactions.iter() {
+                        burned += transaction.burned.unwrap_or_default();
+                    }
+                    burned
                 };
 
                 let mut db_blocks: Vec<DatabaseBlock> = Vec::new();
 
-                db_block.add_rewards(
-                    base_block_reward,
-                    burned,
-                    total_fee_reward,
-                    uncle_rewards,
-                );
+                db_block.add_rewards(&block_reward, burned);
 
                 for uncle in block_uncles.iter_mut() {
-                    let (
-                        base_block_reward,
-                        total_fee_reward,
-                        uncle_rewards,
-                    ) = get_block_reward(
-                        self.chain.id,
-                        uncle,
-                        None,
-                        &[],
-                        true,
-                        Some(db_block.number),
-                    );
-
-                    uncle.add_rewards(
-                        base_block_reward,
-                        U256::zero(),
-                        total_fee_reward,
-                        uncle_rewards,
-                    );
-
-                    db_blocks.push(uncle.to_owned());
-                }
-
-                db_blocks.push(db_block);
-
-                // Insert contracts created through the traces
-                let create_traces: Vec<&DatabaseTrace> = traces
-                    .iter()
-                    .filter(|trace| trace.action_type == TraceType::Create)
-                    .collect();
-
-                for trace in create_traces {
-                    let contract_address = match &trace.address {
-                        Some(contract_address) => contract_address,
-                        None => continue,
+                    let uncle_reward = BlockReward {
+                        base_reward: self
+                            .reward_model
+                            .uncle_reward(uncle, db_block.number),
+                        ..Default::default()
                     };
 
-                    if contracts_map.contains_key(contract_address) {
-                        continue;
-                    }
-
-                    let contract = DatabaseContract {
-                        block_number: trace.block_number,
-                        contract_address: contract_address.to_string(),
-                        chain: self.chain.id,
-                        creator: trace.from.clone().unwrap(),
-                        transaction_hash: trace
-                            .transaction_hash
-                            .clone()
-                            .unwrap(),
-                    };
-
-                    contracts_map
-                        .insert(contract_address.to_string(), contract);
+                    uncle.add_rewards(&uncle_reward, U256::zero());
                 }
 
-                let mut db_erc20_transfers: Vec<DatabaseERC20Transfer> =
-                    Vec::new();
-
-                let mut db_erc721_transfers: Vec<DatabaseERC721Transfer> =
-                    Vec::new();
-
-                let mut db_erc1155_transfers: Vec<
-                    DatabaseERC1155Transfer,
-                > = Vec::new();
-
-                let mut db_dex_trades: Vec<DatabaseDexTrade> = Vec::new();
-
-                for log in db_logs.iter_mut() {
-                    // Check the first topic matches the erc20, erc721, erc1155 or a swap signatures
-                    let topic0 = log.topic0.clone();
-
-                    if topic0 == TRANSFER_EVENTS_SIGNATURE {
-                        // Check if it is a erc20 or a erc721 based on the number of logs
-
-                        // erc721 token transfer events have 3 indexed values.
-                        if log.topic3.is_some() {
-                            let erc721 =
-                                DatabaseERC721Transfer::from_rpc(log);
-
-                            db_erc721_transfers.push(erc721)
-                        } else if log.topic1.is_some()
-                            && log.topic2.is_some()
-                        {
-                            // erc20 token transfer events have 2 indexed values.
-                            let erc20 =
-                                DatabaseERC20Transfer::from_rpc(log);
+                if let Err(err) =
+                    check_reward_traces(&db_block, &block_uncles, &traces)
+                {
+                    warn!("{}", err);
+                }
 
-                            db_erc20_transfers.push(erc20)
-                        }
-                    }
+                let mut db_uncles: Vec<DatabaseUncle> = Vec::new();
 
-                    if topic0 == ERC1155_TRANSFER_SINGLE_EVENT_SIGNATURE
-                        && log.topic1.is_some()
-                        && log.topic2.is_some()
-                        && log.topic3.is_some()
+                if self.datasets.contains(&Dataset::Uncles) {
+                    for (uncle_index, uncle) in
+                        block_uncles.iter().enumerate()
                     {
-                        let log_data = decode_bytes(log.data.clone());
-
-                        let transfer_values = ethabi::decode(
-                            &[ParamType::Uint(256), ParamType::Uint(256)],
-                            &log_data[..],
-                        )
-                        .unwrap();
-
-                        let id = transfer_values[0]
-                            .clone()
-                            .into_uint()
-                            .unwrap();
-
-                        let amount = transfer_values[1]
-                            .clone()
-                            .into_uint()
-                            .unwrap();
-
-                        let erc1155_transfer =
-                            DatabaseERC1155Transfer::from_single_rpc(
-                                log, id, amount,
-                            );
-
-                        db_erc1155_transfers.push(erc1155_transfer);
+                        db_uncles.push(DatabaseUncle::new(
+                            &db_block,
+                            uncle,
+                            uncle_index as u8,
+                        ));
                     }
 
-                    if topic0 == ERC1155_TRANSFER_BATCH_EVENT_SIGNATURE
-                        && log.topic1.is_some()
-                        && log.topic2.is_some()
-                        && log.topic3.is_some()
-                    {
-                        let log_data = decode_bytes(log.data.clone());
-
-                        let transfer_values = ethabi::decode(
-                            &[
-                                ParamType::Array(Box::new(
-                                    ParamType::Uint(256),
-                                )),
-                                ParamType::Array(Box::new(
-                                    ParamType::Uint(256),
-                                )),
-                            ],
-                            &log_data[..],
-                        )
-                        .unwrap();
-
-                        let ids: Vec<U256> = transfer_values[0]
-                            .clone()
-                            .into_array()
-                            .unwrap()
-                            .iter()
-                            .map(|token| {
-                                token.clone().into_uint().unwrap()
-                            })
-                            .collect();
-
-                        let amounts: Vec<U256> = transfer_values[1]
-                            .clone()
-                            .into_array()
-                            .unwrap()
-                            .iter()
-                            .map(|token| {
-                                token.clone().into_uint().unwrap()
-                            })
-                            .collect();
-
-                        let erc1155_transfer =
-                            DatabaseERC1155Transfer::from_batch_rpc(
-                                log, ids, amounts,
-                            );
-
-                        db_erc1155_transfers.push(erc1155_transfer);
-                    }
+                    db_blocks.append(&mut block_uncles);
+                }
 
-                    if topic0 == SWAP_EVENT_SIGNATURE
-                        && log.topic1.is_some()
-                        && log.topic2.is_some()
-                    {
-                        let swap = DatabaseDexTrade::from_v2_rpc(log);
+                db_blocks.push(db_block);
 
-                        db_dex_trades.push(swap);
-                    }
+                // Insert contracts created through the traces
+                insert_trace_contracts(
+                    &mut contracts_map,
+                    &traces,
+                    self.chain.id,
+                );
 
-                    if topic0 == SWAPV3_EVENT_SIGNATURE
-                        && log.topic1.is_some()
-                        && log.topic2.is_some()
-                    {
-                        let swap = DatabaseDexTrade::from_v3_rpc(log);
+                let mut log_events =
+                    decode_log_events(&db_logs, &self.datasets);
 
-                        db_dex_trades.push(swap);
-                    }
+                for decode_error in log_events.decode_errors.iter() {
+                    warn!(
+                        "Unable to decode log {} of transaction {}: {}",
+                        decode_error.log_index,
+                        decode_error.transaction_hash,
+                        decode_error.error
+                    );
                 }
 
                 let db_contracts: Vec<DatabaseContract> = contracts_map
@@ -505,114 +443,336 @@ impl Rpc {
                     block_number,
                 );
 
-                Some((
-                    db_blocks,
-                    db_transactions,
-                    db_logs,
-                    db_contracts,
+                let mut block_data = BlockFetchedData {
+                    authorizations: db_authorizations,
+                    blocks: db_blocks,
+                    contracts: db_contracts,
+                    logs: db_logs,
                     traces,
-                    db_withdrawals,
-                    db_erc20_transfers,
-                    db_erc721_transfers,
-                    db_erc1155_transfers,
-                    db_dex_trades,
-                ))
+                    transactions: db_transactions,
+                    uncles: db_uncles,
+                    withdrawals: db_withdrawals,
+                    ..Default::default()
+                };
+
+                block_data.append(&mut log_events);
+
+                Ok(block_data)
             }
-            None => None,
+            Err(err) => Err(err),
         }
     }
 
-    pub async fn listen_blocks(&self, db: &Database) {
-        let client = self.get_ws_client().await;
-        let client_id = client.request("eth_chainId", rpc_params![]).await;
-        match client_id {
-            Ok(value) => {
-                let chain_id: U256 = match serde_json::from_value(value) {
-                    Ok(value) => value,
-                    Err(_) => {
-                        panic!("unable to get chain id from websocket")
+    // Only resumes from the stored blocks when no backfill runs next to the listener, e.g. with
+    // `--new-blocks-only`, otherwise the heights below the first head are left to the backfill.
+    pub async fn listen_blocks(&self, db: &Database, resume: bool) {
+        let (heads_sender, heads) = mpsc::channel(LIVE_QUEUE_CAPACITY);
+
+        tokio::join!(
+            self.follow_heads(db, heads_sender, resume),
+            self.commit_heads(db, heads)
+        );
+    }
+
+    // Queues new heads until shutdown, dropping `heads` on return so the commit side can drain.
+    async fn follow_heads(
+        &self,
+        db: &Database,
+        heads: mpsc::Sender<u32>,
+        resume: bool,
+    ) {
+        // Resume from the last indexed height so blocks produced while the indexer was down are not skipped.
+        let mut last_head: Option<u32> = match resume {
+            true => db.get_last_indexed_block().await,
+            false => None,
+        };
+        let mut backoff = WS_RECONNECT_MIN_BACKOFF;
+
+        while !self.shutdown.is_requested() {
+            let client = match self.ws_url {
+                Some(_) => match self.get_ws_client().await {
+                    Ok(client) => client,
+                    Err(err) => {
+                        // a websocket of another chain stops the chain
+                        error!("{}", err);
+                        self.shutdown.request();
+                        return;
                     }
-                };
+                },
+                None => None,
+            };
+
+            match client {
+                Some(client) => {
+                    backoff = WS_RECONNECT_MIN_BACKOFF;
 
-                if chain_id.as_u64() != self.chain.id {
-                    panic!("websocket chain id doesn't match with configured chain id")
+                    self.follow_ws_heads(&client, &heads, &mut last_head)
+                        .await;
+
+                    if !self.shutdown.is_requested() {
+                        warn!(
+                            "Websocket head subscription ended, reconnecting."
+                        );
+                    }
+                }
+                None => {
+                    // Keep following the chain over HTTP while the websocket is unavailable
+                    // and retry the websocket once the backoff expires.
+                    let deadline = self
+                        .ws_url
+                        .as_ref()
+                        .map(|_| Instant::now() + backoff);
+
+                    self.poll_heads(&heads, &mut last_head, deadline)
+                        .await;
+
+                    backoff = (backoff * 2).min(WS_RECONNECT_MAX_BACKOFF);
                 }
             }
-            Err(_) => panic!("unable to access websocket"),
         }
+    }
 
-        let mut subscription: Subscription<Block<TxHash>> = client
+    async fn follow_ws_heads(
+        &self,
+        client: &WsClient,
+        heads: &mpsc::Sender<u32>,
+        last_head: &mut Option<u32>,
+    ) {
+        let subscription: Result<Subscription<Block<TxHash>>, _> = client
             .subscribe(
                 "eth_subscribe",
                 rpc_params!["newHeads"],
                 "eth_unsubscribe",
             )
-            .await
-            .expect("unable to start block listener");
+            .await;
 
-        while let Some(block) = subscription.next().await {
-            if block.is_err() {
-                continue;
+        let mut subscription = match subscription {
+            Ok(subscription) => subscription,
+            Err(err) => {
+                warn!("Unable to start block listener: {}", err);
+                return;
+            }
+        };
+
+        loop {
+            let block = tokio::select! {
+                block = subscription.next() => block,
+                _ = self.shutdown.requested() => {
+                    subscription.unsubscribe().await.ok();
+                    return;
+                }
+            };
+
+            let block_number = match block {
+                Some(block) => match block
+                    .ok()
+                    .and_then(|block| block.number)
+                {
+                    Some(block_number) => block_number.as_usize() as u32,
+                    None => continue,
+                },
+                None => return,
+            };
+
+            self.queue_heads(heads, block_number, last_head).await;
+        }
+    }
+
+    async fn poll_heads(
+        &self,
+        heads: &mpsc::Sender<u32>,
+        last_head: &mut Option<u32>,
+        deadline: Option<Instant>,
+    ) {
+        loop {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                return;
             }
-            tokio::spawn({
-                let rpc = self.clone();
-                let db: Database = db.clone();
-                let block = block.unwrap().clone();
-                async move {
-                    let block_number =
-                        block.number.unwrap().as_usize() as u32;
 
-                    info!("New head found {}.", block_number.clone());
+            let chain_head = self.get_last_block().await;
 
-                    // Some chains require a small delay between receiving the head and fetching the block
-                    // to allow the chain and nodes propagate and execute the block data.
+            let is_new_head = match *last_head {
+                Some(last_head) => chain_head > last_head,
+                None => chain_head != 0,
+            };
 
-                    // The list of chains to add delay should be added manually and tested
-                    // Right now this is tested for ETH (1) and BSC (56)
-                    // These values can change depending on network load
+            if is_new_head {
+                self.queue_heads(heads, chain_head, last_head).await;
+            }
+
+            tokio::select! {
+                _ = sleep(self.poll_interval) => (),
+                _ = self.shutdown.requested() => return,
+            }
+        }
+    }
 
-                    // ETH requires 300ms
-                    if rpc.chain.id == 1 {
-                        sleep(Duration::from_millis(300)).await;
+    async fn queue_heads(
+        &self,
+        heads: &mpsc::Sender<u32>,
+        block_number: u32,
+        last_head: &mut Option<u32>,
+    ) {
+        info!("New head found {}.", block_number);
+
+        // Backfill the heights skipped since the last head, one by one and in order.
+        let from = match *last_head {
+            Some(last_head) if block_number > last_head + 1 => {
+                warn!(
+                    "Missed heads {} to {}, backfilling.",
+                    last_head + 1,
+                    block_number - 1
+                );
+
+                last_head + 1
+            }
+            _ => block_number,
+        };
+
+        for number in from..=block_number {
+            // Waits when the pipeline is full, so bursts of heads can't pile up unbounded.
+            let sent = tokio::select! {
+                sent = heads.send(number) => sent.is_ok(),
+                _ = self.shutdown.requested() => false,
+            };
+
+            if !sent {
+                return;
+            }
+        }
+
+        *last_head = Some(block_number);
+    }
+
+    async fn commit_heads(
+        &self,
+        db: &Database,
+        heads: mpsc::Receiver<u32>,
+    ) {
+        // Queued heads not taken before the shutdown are left to the backfill on the next run.
+        let heads = stream::unfold(
+            (heads, self.shutdown.clone()),
+            |(mut heads, shutdown)| async move {
+                if shutdown.is_requested() {
+                    return None;
+                }
+
+                heads
+                    .recv()
+                    .await
+                    .map(|block_number| (block_number, (heads, shutdown)))
+            },
+        );
+
+        // Blocks are fetched with limited parallelism but come out of the stream in height order,
+        // so they are committed one at a time and in order. The stream owns its inputs so the
+        // listener can be spawned.
+        let rpc = self.clone();
+        let mut fetched_heads = Box::pin(
+            heads
+                .map(move |block_number| {
+                    let rpc = rpc.clone();
+
+                    async move {
+                        (block_number, rpc.fetch_head(block_number).await)
                     }
+                })
+                .buffered(LIVE_FETCH_CONCURRENCY),
+        );
+
+        while let Some((block_number, block_data)) =
+            fetched_heads.next().await
+        {
+            // A head that could not be stored is left to the backfill as a missing block.
+            let stored = match block_data {
+                Some(fetched_data) => match db
+                    .store_data(&fetched_data)
+                    .await
+                {
+                    Ok(_) => true,
+                    Err(_) => {
+                        warn!("Unable to store head {}.", block_number);
+                        false
+                    }
+                },
+                None => {
+                    warn!("Unable to fetch head {}.", block_number);
+                    false
+                }
+            };
 
-                    // BSC requires 4s
-                    if rpc.chain.id == 56 {
-                        sleep(Duration::from_secs(4)).await;
+            let checkpoint = db
+                .update_info_for_sync(TIP_SYNC_JOB, |info_for_sync| {
+                    // Heights ahead of the checkpoint are left to the backfill, which will cover them in order.
+                    if block_number > info_for_sync.end_block {
+                        return false;
                     }
 
-                    let block_data =
-                        rpc.fetch_block(&block_number, &rpc.chain).await;
-                    if let Some((
-                        blocks,
-                        transactions,
-                        logs,
-                        contracts,
-                        traces,
-                        withdrawals,
-                        erc20_transfers,
-                        erc721_transfers,
-                        erc1155_transfers,
-                        dex_trades,
-                    )) = block_data
-                    {
-                        let fetched_data = BlockFetchedData {
-                            blocks,
-                            contracts,
-                            logs,
-                            traces,
-                            transactions,
-                            withdrawals,
-                            erc20_transfers,
-                            erc721_transfers,
-                            erc1155_transfers,
-                            dex_trades,
-                        };
+                    if block_number == info_for_sync.end_block {
+                        info_for_sync.end_block = block_number + 1;
+                    }
+
+                    info_for_sync.missing_blocks.retain(|missing_block| {
+                        *missing_block != block_number
+                    });
 
-                        db.store_data(&fetched_data).await;
+                    if !stored {
+                        info_for_sync.missing_blocks.push(block_number);
                     }
-                }
-            });
+
+                    true
+                })
+                .await;
+
+            if checkpoint.is_err() {
+                warn!(
+                    "Unable to store checkpoint for head {}.",
+                    block_number
+                );
+            }
+        }
+    }
+
+    async fn fetch_head(
+        &self,
+        block_number: u32,
+    ) -> Option<BlockFetchedData> {
+        // Some chains require a small delay between receiving the head and fetching the block
+        // to allow the chain and nodes propagate and execute the block data.
+        sleep(Duration::from_millis(self.chain.head_delay)).await;
+
+        let deadline = Instant::now() + self.head_timeout;
+        let mut backoff = HEAD_RETRY_MIN_BACKOFF;
+
+        loop {
+            // The block, its receipts or its traces may not be available yet on a slow node.
+            let err =
+                match self.fetch_block(&block_number, &self.chain).await {
+                    Ok(block_data) => return Some(block_data),
+                    Err(err) => err,
+                };
+
+            if Instant::now() + backoff > deadline {
+                error!(
+                    "Head {} not ready after {:?}, giving up: {}",
+                    block_number, self.head_timeout, err
+                );
+                return None;
+            }
+
+            if self.shutdown.is_requested() {
+                return None;
+            }
+
+            debug!(
+                "Head {} not ready yet, retrying in {:?}: {}",
+                block_number, backoff, err
+            );
+
+            sleep(backoff).await;
+
+            backoff = (backoff * 2).min(HEAD_RETRY_MAX_BACKOFF);
         }
     }
 
@@ -622,22 +782,58 @@ impl Rpc {
         client
     }
 
-    async fn get_ws_client(&self) -> WsClient {
-        let url = self.ws_url.clone().unwrap();
-        let client_wss: WsClient =
-            WsClientBuilder::default().build(url).await.unwrap();
-        client_wss
+    // none while the websocket is unavailable, an error on a chain mismatch
+    async fn get_ws_client(
+        &self,
+    ) -> Result<Option<WsClient>, IndexerError> {
+        let url = match self.ws_url.clone() {
+            Some(url) => url,
+            None => return Ok(None),
+        };
+
+        let client: WsClient =
+            match WsClientBuilder::default().build(url).await {
+                Ok(client) => client,
+                Err(err) => {
+                    warn!("Unable to connect to websocket: {}", err);
+                    return Ok(None);
+                }
+            };
+
+        let client_id = client.request("eth_chainId", rpc_params![]).await;
+
+        let chain_id: U256 =
+            match client_id.map(serde_json::from_value::<U256>) {
+                Ok(Ok(chain_id)) => chain_id,
+                _ => {
+                    warn!("Unable to get chain id from websocket.");
+                    return Ok(None);
+                }
+            };
+
+        if chain_id.as_u64() != self.chain.id {
+            return Err(IndexerError::Rpc(format!(
+                "websocket chain id {} doesn't match with configured chain id {}",
+                chain_id, self.chain.id
+            )));
+        }
+
+        Ok(Some(client))
     }
 
     async fn get_block(
         &self,
         block_number: &u32,
-    ) -> Option<(
-        DatabaseBlock,
-        Vec<DatabaseTransaction>,
-        Vec<DatabaseWithdrawal>,
-        Vec<DatabaseBlock>,
-    )> {
+    ) -> Result<
+        (
+            DatabaseBlock,
+            Vec<DatabaseTransaction>,
+            Vec<DatabaseAuthorization>,
+            Vec<DatabaseWithdrawal>,
+            Vec<DatabaseBlock>,
+        ),
+        IndexerError,
+    > {
         let client = self.get_client();
 
         let raw_block = client
@@ -661,6 +857,7 @@ impl Rpc {
                         );
 
                         let mut db_transactions = Vec::new();
+                        let mut db_authorizations = Vec::new();
 
                         for transaction in block.transactions.iter() {
                             let db_transaction =
@@ -670,7 +867,15 @@ impl Rpc {
                                     db_block.timestamp,
                                 );
 
-                            db_transactions.push(db_transaction)
+                            db_transactions.push(db_transaction);
+
+                            db_authorizations.append(
+                                &mut DatabaseAuthorization::from_rpc(
+                                    transaction,
+                                    self.chain.id,
+                                    db_block.timestamp,
+                                ),
+                            );
                         }
 
                         let mut db_withdrawals: Vec<DatabaseWithdrawal> =
@@ -692,9 +897,11 @@ impl Rpc {
                             }
                         }
 
+                        // Uncles are fetched even when they are not indexed, the rewards of the
+                        // block depend on them, and a missing one fails the block.
                         let mut block_uncles = Vec::new();
 
-                        for (i, _) in db_block.uncles.iter().enumerate() {
+                        for i in 0..db_block.uncles.len() {
                             let raw_uncle = client
                                 .request(
                                     "eth_getUncleByBlockNumberAndIndex",
@@ -703,54 +910,56 @@ impl Rpc {
                                         format!("0x{:x}", i)
                                     ],
                                 )
-                                .await;
-                            match raw_uncle {
-                                Ok(value) => {
-                                    let block: Result<
-                                        Block<TxHash>,
-                                        Error,
-                                    > = serde_json::from_value(value);
-
-                                    match block {
-                                        Ok(block) => {
-                                            let db_block =
-                                                DatabaseBlock::from_rpc(
-                                                    &block,
-                                                    self.chain.id,
-                                                    true,
-                                                );
-
-                                            block_uncles.push(db_block)
-                                        }
-                                        Err(err) => {
-                                            println!("{}", err)
-                                        }
-                                    }
-                                }
-                                Err(err) => {
-                                    println!("{}", err)
-                                }
-                            }
+                                .await
+                                .map_err(|err| {
+                                    IndexerError::Rpc(format!(
+                                        "unable to get uncle {} of block {}: {}",
+                                        i, block_number, err
+                                    ))
+                                })?;
+
+                            let uncle: Block<TxHash> =
+                                serde_json::from_value(raw_uncle).map_err(
+                                    |err| {
+                                        IndexerError::Rpc(format!(
+                                            "unable to decode uncle {} of block {}: {}",
+                                            i, block_number, err
+                                        ))
+                                    },
+                                )?;
+
+                            block_uncles.push(DatabaseBlock::from_rpc(
+                                &uncle,
+                                self.chain.id,
+                                true,
+                            ));
                         }
 
-                        Some((
+                        Ok((
                             db_block,
                             db_transactions,
+                            db_authorizations,
                             db_withdrawals,
                             block_uncles,
                         ))
                     }
-                    Err(_) => None,
+                    Err(err) => Err(IndexerError::Rpc(format!(
+                        "unable to decode block {}: {}",
+                        block_number, err
+                    ))),
                 }
             }
-            Err(_) => None,
+            Err(err) => Err(IndexerError::Rpc(format!(
+                "unable to get block {}: {}",
+                block_number, err
+            ))),
         }
     }
 
     async fn get_block_traces(
         &self,
         block_number: &u32,
-    ) -> Vec<DatabaseTrace> {
+    ) -> Result<Vec<DatabaseTrace>, IndexerError> {
         let client = self.get_client();
 
         let raw_block = client
@@ -778,12 +987,18 @@ impl Rpc {
                             db_traces.push(db_trace)
                         }
 
-                        db_traces
+                        Ok(db_traces)
                     }
-                    Err(_) => Vec::new(),
+                    Err(err) => Err(IndexerError::Rpc(format!(
+                        "unable to decode traces of block {}: {}",
+                        block_number, err
+                    ))),
                 }
             }
-            Err(_) => Vec::new(),
+            Err(err) => Err(IndexerError::Rpc(format!(
+                "unable to get traces of block {}: {}",
+                block_number, err
+            ))),
         }
     }
 
@@ -792,15 +1007,17 @@ impl Rpc {
         transaction: String,
         transaction_timestamp: u32,
         block_number: &u32,
-    ) -> Option<(
-        TransactionReceipt,
-        Vec<DatabaseLog>,
-        Option<DatabaseContract>,
-    )> {
+    ) -> Result<
+        (TransactionReceipt, Vec<DatabaseLog>, Option<DatabaseContract>),
+        IndexerError,
+    > {
         let client = self.get_client();
 
         let raw_receipt = client
-            .request("eth_getTransactionReceipt", rpc_params![transaction])
+            .request(
+                "eth_getTransactionReceipt",
+                rpc_params![transaction.clone()],
+            )
             .await;
 
         match raw_receipt {
@@ -846,12 +1063,18 @@ impl Rpc {
                             db_transaction_logs.push(db_log)
                         }
 
-                        Some((receipt, db_transaction_logs, db_contract))
+                        Ok((receipt, db_transaction_logs, db_contract))
                     }
-                    Err(_) => None,
+                    Err(err) => Err(IndexerError::Rpc(format!(
+                        "unable to decode receipt {}: {}",
+                        transaction, err
+                    ))),
                 }
             }
-            Err(_) => None,
+            Err(err) => Err(IndexerError::Rpc(format!(
+                "unable to get receipt {}: {}",
+                transaction, err
+            ))),
         }
     }
 
@@ -859,11 +1082,10 @@ impl Rpc {
         &self,
         block_number: &u32,
         block_timestamp: u32,
-    ) -> Option<(
-        Vec<TransactionReceipt>,
-        Vec<DatabaseLog>,
-        Vec<DatabaseContract>,
-    )> {
+    ) -> Result<
+        (Vec<TransactionReceipt>, Vec<DatabaseLog>, Vec<DatabaseContract>),
+        IndexerError,
+    > {
         let client = self.get_client();
 
         let raw_receipts = client
@@ -898,11 +1120,8 @@ impl Rpc {
                                     )
                                 });
 
-                            match db_contract.is_some() {
-                                true => {
-                                    db_contracts.push(db_contract.unwrap())
-                                }
-                                false => (),
+                            if let Some(db_contract) = db_contract {
+                                db_contracts.push(db_contract)
                             }
 
                             for log in receipt.logs.iter() {
@@ -919,148 +1138,507 @@ impl Rpc {
                             db_receipts.push(receipt);
                         }
 
-                        Some((
+                        Ok((
                             db_receipts,
                             db_transaction_logs,
                             db_contracts,
                         ))
                     }
-                    Err(_) => None,
+                    Err(err) => Err(IndexerError::Rpc(format!(
+                        "unable to decode receipts of block {}: {}",
+                        block_number, err
+                    ))),
+                }
+            }
+            Err(err) => Err(IndexerError::Rpc(format!(
+                "unable to get receipts of block {}: {}",
+                block_number, err
+            ))),
+        }
+    }
+}
+
+// Adds the contracts created by the create traces and not found in the receipts.
+fn insert_trace_contracts(
+    contracts_map: &mut HashMap<String, DatabaseContract>,
+    traces: &[DatabaseTrace],
+    chain: u64,
+) {
+    let create_traces = traces
+        .iter()
+        .filter(|trace| trace.action_type == TraceType::Create);
+
+    for trace in create_traces {
+        let (contract_address, creator, transaction_hash) =
+            match (&trace.address, &trace.from, &trace.transaction_hash) {
+                (
+                    Some(contract_address),
+                    Some(creator),
+                    Some(transaction_hash),
+                ) => (contract_address, creator, transaction_hash),
+                _ => continue,
+            };
+
+        if contracts_map.contains_key(contract_address) {
+            continue;
+        }
+
+        let contract = DatabaseContract {
+            block_number: trace.block_number,
+            contract_address: contract_address.to_string(),
+            chain,
+            creator: creator.to_string(),
+            transaction_hash: transaction_hash.to_string(),
+        };
+
+        contracts_map.insert(contract_address.to_string(), contract);
+    }
+}
+
+// Heights to backfill: first the blocks that failed previously, then the chain from the checkpoint
+// to `last_block` or, when unbounded, to the tip.
+struct BackfillRange {
+    chain_head: u32,
+    last_block: Option<u32>,
+    next_block: u32,
+    retries: std::vec::IntoIter<u32>,
+}
+
+impl BackfillRange {
+    async fn next(&mut self, rpc: &Rpc) -> Option<u32> {
+        // Stop taking new heights, the ones already fetched are still committed.
+        if rpc.shutdown.is_requested() {
+            return None;
+        }
+
+        if let Some(block_number) = self.retries.next() {
+            return Some(block_number);
+        }
+
+        if self
+            .last_block
+            .is_some_and(|last_block| self.next_block > last_block)
+        {
+            return None;
+        }
+
+        while self.next_block > self.chain_head {
+            self.chain_head = rpc.get_last_block().await;
+
+            if self.next_block > self.chain_head {
+                // Caught up with the chain, wait for new blocks before polling again.
+                tokio::select! {
+                    _ = sleep(rpc.poll_interval) => (),
+                    _ = rpc.shutdown.requested() => return None,
                 }
             }
-            Err(_) => None,
         }
+
+        let block_number = self.next_block;
+
+        self.next_block += 1;
+
+        Some(block_number)
     }
 }
 
+// A fetched block on its way to the writer with the time it took to fetch, holding its share of
+// the memory budget until stored.
+type BackfillItem =
+    (u32, Option<BlockFetchedData>, Duration, OwnedSemaphorePermit);
+
 pub async fn sync_chain(rpc: &Rpc, db: &Database, config: &Config) {
     info!("sync chain");
 
+    let job = config.sync_job.as_str();
+    let last_block = config.range_end_block();
+
     // load initial info for starting synchronizing data
-    let info_for_sync = db.get_info_for_sync().await;
+    let info_for_sync = db.get_info_for_sync(job).await;
     let mut missing_blocks: Vec<u32> = vec![];
-    let mut start_block;
-    let mut end_block = 0;
-    if info_for_sync.len() as u32 == 0 {
-        // If there are no indexed blocks, insert the genesis transactions
-        let genesis_transactions =
-            get_genesis_allocations(config.chain.clone());
-        db.store_items(
-            &genesis_transactions,
-            DatabaseTables::Transactions.as_str(),
-        )
-        .await;
+    let mut end_block = config.start_block;
+    if info_for_sync.is_empty()
+        && config.start_block == 0
+        && (config.datasets.contains(&Dataset::Transactions)
+            || config.datasets.contains(&Dataset::Contracts))
+    {
+        // If there are no indexed blocks, insert the genesis transactions and contracts
+        let genesis_data = match get_genesis_allocations(&config.chain) {
+            Ok(genesis_data) => genesis_data,
+            Err(err) => {
+                error!("{}", err);
+                return;
+            }
+        };
+        if db.store_data(&genesis_data).await.is_err() {
+            error!("Unable to store the genesis allocations.");
+            return;
+        }
     }
-    if info_for_sync.len() as u32 != 0 {
-        missing_blocks = info_for_sync[0].missing_blocks.clone();
-        end_block = info_for_sync[0].end_block;
+    if let Some(info_for_sync) = info_for_sync.first() {
+        missing_blocks = info_for_sync.missing_blocks.clone();
+        end_block = end_block.max(info_for_sync.end_block);
     }
 
-    loop {
-        // get the last block number from the chain
-        let last_block = rpc.get_last_block().await;
-        info!("{}", last_block.clone());
-
-        while end_block < last_block {
-            start_block = end_block;
-            end_block = start_block + config.batch_size as u32
-                - missing_blocks.len() as u32;
-            if end_block > last_block {
-                end_block = last_block;
-            }
-            info!("new importing: {} - {}", start_block, end_block);
+    info!(
+        "Backfilling job {} from block {} to {} with {} missing blocks.",
+        job,
+        end_block,
+        last_block.map_or("tip".to_string(), |block| block.to_string()),
+        missing_blocks.len()
+    );
+
+    let range = BackfillRange {
+        chain_head: 0,
+        last_block,
+        next_block: end_block,
+        retries: missing_blocks.into_iter(),
+    };
+
+    let (sender, receiver) = mpsc::channel(config.fetch_concurrency);
+
+    tokio::join!(
+        fetch_backfill(rpc, config, range, sender),
+        write_backfill(db, config, receiver, true)
+    );
+
+    if let Some(info_for_sync) = db.get_info_for_sync(job).await.first() {
+        if !info_for_sync.missing_blocks.is_empty() {
+            warn!(
+                "Job {} finished with {} missing blocks, run it again to retry them.",
+                job,
+                info_for_sync.missing_blocks.len()
+            );
+        }
+    }
 
-            let mut work = vec![];
-            let mut tmp_list = vec![];
-            for i in start_block..end_block {
-                missing_blocks.push(i);
+    if rpc.shutdown.is_requested() {
+        info!("Job {} stopped, fetched blocks committed.", job);
+    } else {
+        info!("Job {} finished.", job);
+    }
+}
+
+// Finds the blocks between the start and end block that are missing or only partially stored,
+// reports them and fetches them again.
+pub async fn audit_chain(rpc: &Rpc, db: &Database, config: &Config) {
+    info!("audit chain");
+
+    let last_block = match config.range_end_block() {
+        Some(last_block) => last_block,
+        None => match db.get_last_indexed_block().await {
+            Some(last_block) => last_block,
+            None => {
+                warn!("No indexed blocks to audit.");
+                return;
             }
-            for i in 0..missing_blocks.len() {
-                work.push(
-                    rpc.fetch_block(&missing_blocks[i], &config.chain),
+        },
+    };
+
+    let indexed_blocks = db.get_indexed_blocks().await;
+
+    let gaps: Vec<u32> = (config.start_block..=last_block)
+        .filter(|block_number| !indexed_blocks.contains(block_number))
+        .collect();
+
+    let mut partial_blocks: Vec<u32> = vec![];
+    let mut window_start = config.start_block;
+    while window_start <= last_block {
+        let window_end =
+            window_start.saturating_add(AUDIT_WINDOW - 1).min(last_block);
+
+        for block in db.get_blocks_audit(window_start, window_end).await {
+            if block.is_partial(&config.datasets) {
+                warn!(
+                    "Block {} is partial: {}/{} transactions, {} receipts, {} logs (bloom {}).",
+                    block.number,
+                    block.transactions,
+                    block.expected_transactions,
+                    block.receipts,
+                    block.logs,
+                    if block.has_logs { "not empty" } else { "empty" }
                 );
+
+                partial_blocks.push(block.number);
             }
+        }
 
-            // let test_block_number = start_block.clone();
-            // let test =
-            //     rpc.fetch_block(&test_block_number, &config.chain).await;
-            // info!("test result: {:?}", test);
-
-            let results = join_all(work).await;
-
-            let mut fetched_data = BlockFetchedData {
-                blocks: Vec::new(),
-                contracts: Vec::new(),
-                logs: Vec::new(),
-                traces: Vec::new(),
-                transactions: Vec::new(),
-                withdrawals: Vec::new(),
-                erc20_transfers: Vec::new(),
-                erc721_transfers: Vec::new(),
-                erc1155_transfers: Vec::new(),
-                dex_trades: Vec::new(),
-            };
+        if window_end == u32::MAX {
+            break;
+        }
 
-            info!("{:?}", results);
-            for (index, result) in results.into_iter().enumerate() {
-                match result {
-                    Some((
-                        mut blocks,
-                        mut transactions,
-                        mut logs,
-                        mut contracts,
-                        mut traces,
-                        mut withdrawals,
-                        mut erc20_transfers,
-                        mut erc721_transfers,
-                        mut erc1155_transfers,
-                        mut dex_trades,
-                    )) => {
-                        fetched_data.blocks.append(&mut blocks);
-                        fetched_data
-                            .transactions
-                            .append(&mut transactions);
-                        fetched_data.logs.append(&mut logs);
-                        fetched_data.contracts.append(&mut contracts);
-                        fetched_data.traces.append(&mut traces);
-                        fetched_data.withdrawals.append(&mut withdrawals);
-                        fetched_data
-                            .erc20_transfers
-                            .append(&mut erc20_transfers);
-                        fetched_data
-                            .erc721_transfers
-                            .append(&mut erc721_transfers);
-                        fetched_data
-                            .erc1155_transfers
-                            .append(&mut erc1155_transfers);
-                        fetched_data.dex_trades.append(&mut dex_trades);
-                    }
-                    None => tmp_list.push(missing_blocks[index]),
+        window_start = window_end + 1;
+    }
+
+    info!(
+        "Audited blocks {} to {}: {} missing blocks, {} partial blocks.",
+        config.start_block,
+        last_block,
+        gaps.len(),
+        partial_blocks.len()
+    );
+
+    for (start, end) in block_ranges(&gaps) {
+        warn!("Missing blocks {} to {}.", start, end);
+    }
+
+    let mut repair_blocks = gaps;
+    repair_blocks.append(&mut partial_blocks);
+    repair_blocks.sort_unstable();
+
+    if repair_blocks.is_empty() {
+        return;
+    }
+
+    info!("Fetching {} blocks again.", repair_blocks.len());
+
+    // Only the audited blocks are fetched, the range itself is already past its last block.
+    let range = BackfillRange {
+        chain_head: 0,
+        last_block: Some(0),
+        next_block: 1,
+        retries: repair_blocks.into_iter(),
+    };
+
+    let (sender, receiver) = mpsc::channel(config.fetch_concurrency);
+
+    // Repairs only update the missing blocks, the audited range may reach past the checkpoint.
+    tokio::join!(
+        fetch_backfill(rpc, config, range, sender),
+        write_backfill(db, config, receiver, false)
+    );
+
+    info!("Audit finished.");
+}
+
+// Groups sorted block numbers into inclusive ranges of consecutive blocks.
+fn block_ranges(blocks: &[u32]) -> Vec<(u32, u32)> {
+    let mut ranges: Vec<(u32, u32)> = vec![];
+
+    for &block in blocks {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == block => *end = block,
+            _ => ranges.push((block, block)),
+        }
+    }
+
+    ranges
+}
+
+async fn fetch_backfill(
+    rpc: &Rpc,
+    config: &Config,
+    range: BackfillRange,
+    sender: mpsc::Sender<BackfillItem>,
+) {
+    // Memory budget for fetched blocks not stored yet, in KiB.
+    let memory_budget = (config.max_pending_memory * 1024) as u32;
+    let memory = Arc::new(Semaphore::new(memory_budget as usize));
+
+    // The streams own their inputs, so the backfill future can be spawned.
+    let heights = stream::unfold(
+        (range, rpc.clone()),
+        |(mut range, rpc)| async move {
+            range
+                .next(&rpc)
+                .await
+                .map(|block_number| (block_number, (range, rpc)))
+        },
+    );
+
+    let rpc = rpc.clone();
+    let mut fetched_blocks = Box::pin(
+        heights
+            .map(move |block_number| {
+                let rpc = rpc.clone();
+
+                async move {
+                    let started = Instant::now();
+
+                    let block_data = rpc
+                        .fetch_block(&block_number, &rpc.chain)
+                        .await
+                        .map_err(|err| {
+                            warn!(
+                                "Unable to fetch block {}: {}",
+                                block_number, err
+                            )
+                        })
+                        .ok();
+
+                    (block_number, block_data, started.elapsed())
+                }
+            })
+            .buffered(config.fetch_concurrency),
+    );
+
+    while let Some((block_number, block_data, fetch_latency)) =
+        fetched_blocks.next().await
+    {
+        let size = block_data
+            .as_ref()
+            .map_or(0, |block_data| block_data.estimated_size() / 1024);
+
+        // Stop fetching while the writer is holding too much data.
+        let permit = memory
+            .clone()
+            .acquire_many_owned((size as u32).clamp(1, memory_budget))
+            .await
+            .expect("memory budget semaphore closed");
+
+        if sender
+            .send((block_number, block_data, fetch_latency, permit))
+            .await
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+async fn write_backfill(
+    db: &Database,
+    config: &Config,
+    mut receiver: mpsc::Receiver<BackfillItem>,
+    advance: bool,
+) {
+    // Batches may hold at most half of the memory budget, so the fetchers keep working while a
+    // batch is stored.
+    let mut sizer = BatchSizer::new(
+        config.batch_size,
+        config.min_batch_size,
+        config.max_batch_size,
+        config.max_pending_memory * 1024 * 1024 / 2,
+    );
+
+    let mut fetched_data = BlockFetchedData::new();
+    let mut attempted_blocks: Vec<u32> = Vec::new();
+    let mut missing_blocks: Vec<u32> = Vec::new();
+    let mut fetch_latency = Duration::ZERO;
+    let mut permits: Vec<OwnedSemaphorePermit> = Vec::new();
+
+    loop {
+        // Flush partial batches when the fetchers are idle, e.g. once synced with the tip.
+        let item = match timeout(BACKFILL_FLUSH_INTERVAL, receiver.recv())
+            .await
+        {
+            Ok(Some(item)) => Some(item),
+            Ok(None) => break,
+            Err(_) => None,
+        };
+
+        let is_idle = item.is_none();
+
+        if let Some((block_number, block_data, latency, permit)) = item {
+            match block_data {
+                Some(mut block_data) => {
+                    fetched_data.append(&mut block_data)
                 }
+                None => missing_blocks.push(block_number),
             }
 
-            db.store_data(&fetched_data).await;
+            attempted_blocks.push(block_number);
+            fetch_latency += latency;
+            permits.push(permit);
+        }
 
-            for block in fetched_data.blocks.iter() {
-                info!("block_number {}", block.clone().number);
+        if attempted_blocks.len() >= sizer.batch_size()
+            || is_idle && !attempted_blocks.is_empty()
+        {
+            let started = Instant::now();
+
+            if !commit_backfill(
+                db,
+                &config.sync_job,
+                &fetched_data,
+                &attempted_blocks,
+                &missing_blocks,
+                advance,
+            )
+            .await
+            {
+                return;
             }
 
-            missing_blocks.clear();
-            for tmp in tmp_list {
-                missing_blocks.push(tmp);
+            // Idle flushes are cut short by the fetchers, not by the size of the blocks.
+            if !is_idle {
+                sizer.observe(&BatchStats {
+                    blocks: attempted_blocks.len(),
+                    bytes: fetched_data.estimated_size(),
+                    fetch_latency,
+                    missing_blocks: missing_blocks.len(),
+                    rows: fetched_data.rows(),
+                    store_latency: started.elapsed(),
+                });
             }
 
-            info!("missing blocks: {:?}", missing_blocks);
-            let info_for_sync = DatabaseInfoForSync {
-                end_block,
-                missing_blocks: missing_blocks.clone(),
-                timestamp: Utc::now().timestamp() as u32,
-            };
+            fetched_data = BlockFetchedData::new();
+            attempted_blocks.clear();
+            missing_blocks.clear();
+            fetch_latency = Duration::ZERO;
+            permits.clear();
+        }
+    }
 
-            let lists = vec![info_for_sync];
+    if !attempted_blocks.is_empty() {
+        commit_backfill(
+            db,
+            &config.sync_job,
+            &fetched_data,
+            &attempted_blocks,
+            &missing_blocks,
+            advance,
+        )
+        .await;
+    }
+}
 
-            db.store_items(&lists, DatabaseTables::InfoForSync.as_str())
-                .await;
-        }
+// Stores a batch and then advances the checkpoint past it, unless `advance` is false and only its
+// missing blocks are updated. On failure the checkpoint is left at the last committed batch and
+// the backfill stops, so a restart replays the batch from there.
+async fn commit_backfill(
+    db: &Database,
+    job: &str,
+    fetched_data: &BlockFetchedData,
+    attempted_blocks: &[u32],
+    missing_blocks: &[u32],
+    advance: bool,
+) -> bool {
+    if db.store_data(fetched_data).await.is_err() {
+        error!(
+            "Unable to store blocks {} to {}, stopping at the last committed batch.",
+            attempted_blocks.iter().min().unwrap_or(&0),
+            attempted_blocks.iter().max().unwrap_or(&0)
+        );
+        return false;
     }
+
+    let end_block =
+        attempted_blocks.iter().max().map_or(0, |block| block + 1);
+
+    info!(
+        "Stored {} blocks up to {}, missing blocks: {:?}",
+        attempted_blocks.len() - missing_blocks.len(),
+        end_block,
+        missing_blocks
+    );
+
+    db.update_info_for_sync(job, |info_for_sync| {
+        if advance {
+            info_for_sync.end_block =
+                info_for_sync.end_block.max(end_block);
+        }
+
+        // Keep the blocks the live pipeline failed on while this batch was running.
+        info_for_sync.missing_blocks.retain(|missing_block| {
+            !attempted_blocks.contains(missing_block)
+        });
+        info_for_sync
+            .missing_blocks
+            .extend(missing_blocks.iter().copied());
+
+        true
+    })
+    .await
+    .is_ok()
 }
@@ -0,0 +1,217 @@
+use super::insert_trace_contracts;
+use crate::{
+    configs::{Config, Dataset},
+    db::{
+        models::{
+            contract::DatabaseContract, log::DatabaseLog,
+            trace::DatabaseTrace, transaction::DatabaseTransaction,
+        },
+        Database, DatabaseTables,
+    },
+    errors::IndexerError,
+    utils::{
+        decode::decode_log_events,
+        events::{
+            ERC1155_TRANSFER_BATCH_EVENT_SIGNATURE,
+            ERC1155_TRANSFER_SINGLE_EVENT_SIGNATURE,
+            SWAPV3_EVENT_SIGNATURE, SWAP_EVENT_SIGNATURE,
+            TRANSFER_EVENTS_SIGNATURE,
+        },
+    },
+};
+use log::{error, info, warn};
+use std::collections::{HashMap, HashSet};
+
+// Amount of blocks whose stored logs and traces are read and derived together.
+const REDERIVE_WINDOW: u32 = 1_000;
+
+// Derives again the tables built from the logs and traces of the blocks between the start and end
+// block, replacing their rows, e.g. after a decoder is added or fixed.
+pub async fn rederive_chain(db: &Database, config: &Config) {
+    info!("rederive chain");
+
+    let last_block = match config.range_end_block() {
+        Some(last_block) => last_block,
+        None => match db.get_last_indexed_block().await {
+            Some(last_block) => last_block,
+            None => {
+                warn!("No indexed blocks to derive again.");
+                return;
+            }
+        },
+    };
+
+    let mut window_start = config.start_block;
+    while window_start <= last_block {
+        let window_end = window_start
+            .saturating_add(REDERIVE_WINDOW - 1)
+            .min(last_block);
+
+        match rederive_window(db, config, window_start, window_end).await {
+            Ok(rows) => info!(
+                "Derived {} rows of blocks {} to {}.",
+                rows, window_start, window_end
+            ),
+            Err(err) => {
+                error!(
+                    "Unable to derive blocks {} to {}: {}, run it again from block {}.",
+                    window_start, window_end, err, window_start
+                );
+                return;
+            }
+        }
+
+        if window_end == u32::MAX {
+            break;
+        }
+
+        window_start = window_end + 1;
+    }
+
+    info!("Rederive finished.");
+}
+
+// Replaces the derived rows of the blocks from `start_block` to `end_block` (inclusive) with the
+// ones derived from the stored rows, returning the amount of rows written.
+async fn rederive_window(
+    db: &Database,
+    config: &Config,
+    start_block: u32,
+    end_block: u32,
+) -> Result<usize, IndexerError> {
+    let logs: Vec<DatabaseLog> = db
+        .get_range_items(DatabaseTables::Logs, start_block, end_block, "1")
+        .await?;
+
+    let mut derived_data = decode_log_events(&logs, &config.datasets);
+
+    // Without stored logs there is nothing to derive from, the rows stored from the fetched
+    // receipts are kept.
+    let mut replaced_tables: Vec<(DatabaseTables, String)> = vec![];
+    if !logs.is_empty() {
+        for (dataset, table) in [
+            (Dataset::DexTrades, DatabaseTables::DexTrades),
+            (Dataset::Erc1155, DatabaseTables::Erc1155Transfers),
+            (Dataset::Erc20, DatabaseTables::Erc20Transfers),
+            (Dataset::Erc721, DatabaseTables::Erc721Transfers),
+        ] {
+            if config.datasets.contains(&dataset) {
+                replaced_tables.push((table, String::from("1")));
+            }
+        }
+
+        if let Some(filter) = decode_errors_filter(
+            &config.datasets,
+            config.chain.id,
+            start_block,
+            end_block,
+        ) {
+            replaced_tables.push((DatabaseTables::DecodeErrors, filter));
+        }
+    }
+
+    if config.datasets.contains(&Dataset::Contracts) {
+        let mut contracts_map: HashMap<String, DatabaseContract> =
+            HashMap::new();
+
+        let transactions: Vec<DatabaseTransaction> = db
+            .get_range_items(
+                DatabaseTables::Transactions,
+                start_block,
+                end_block,
+                "contract_created IS NOT NULL",
+            )
+            .await?;
+
+        for transaction in transactions.iter() {
+            if let Some(contract) =
+                DatabaseContract::from_transaction(transaction)
+            {
+                contracts_map
+                    .insert(contract.contract_address.clone(), contract);
+            }
+        }
+
+        let traces: Vec<DatabaseTrace> = db
+            .get_range_items(
+                DatabaseTables::Traces,
+                start_block,
+                end_block,
+                "action_type = 'create'",
+            )
+            .await?;
+
+        insert_trace_contracts(
+            &mut contracts_map,
+            &traces,
+            config.chain.id,
+        );
+
+        derived_data.contracts = contracts_map.into_values().collect();
+
+        // The genesis allocations are not derived from any stored row.
+        replaced_tables.push((
+            DatabaseTables::Contracts,
+            String::from("transaction_hash NOT LIKE '%_GENESIS_%'"),
+        ));
+    }
+
+    for (table, filter) in replaced_tables {
+        db.delete_range(table, start_block, end_block, &filter).await?;
+    }
+
+    // Only the derived tables are filled, the stored blocks and sources are left untouched.
+    db.store_data(&derived_data).await?;
+
+    Ok(derived_data.rows())
+}
+
+// Decode errors of the logs of the selected datasets, the errors of the others are kept. Transfer
+// logs are told apart by their fourth topic, which only erc721 transfers have.
+fn decode_errors_filter(
+    datasets: &HashSet<Dataset>,
+    chain: u64,
+    start_block: u32,
+    end_block: u32,
+) -> Option<String> {
+    let transfer_logs = |topic3: &str| {
+        format!(
+            "topic0 = '{0}' AND (transaction_hash, log_index) IN (SELECT transaction_hash, log_index FROM logs WHERE chain = {1} AND block_number BETWEEN {2} AND {3} AND topic0 = '{0}' AND topic3 {4})",
+            TRANSFER_EVENTS_SIGNATURE, chain, start_block, end_block, topic3
+        )
+    };
+
+    let mut conditions: Vec<String> = vec![];
+
+    match (
+        datasets.contains(&Dataset::Erc20),
+        datasets.contains(&Dataset::Erc721),
+    ) {
+        (true, true) => conditions
+            .push(format!("topic0 = '{}'", TRANSFER_EVENTS_SIGNATURE)),
+        (true, false) => conditions.push(transfer_logs("IS NULL")),
+        (false, true) => conditions.push(transfer_logs("IS NOT NULL")),
+        (false, false) => (),
+    }
+
+    if datasets.contains(&Dataset::Erc1155) {
+        conditions.push(format!(
+            "topic0 IN ('{}', '{}')",
+            ERC1155_TRANSFER_SINGLE_EVENT_SIGNATURE,
+            ERC1155_TRANSFER_BATCH_EVENT_SIGNATURE
+        ));
+    }
+
+    if datasets.contains(&Dataset::DexTrades) {
+        conditions.push(format!(
+            "topic0 IN ('{}', '{}')",
+            SWAP_EVENT_SIGNATURE, SWAPV3_EVENT_SIGNATURE
+        ));
+    }
+
+    if conditions.is_empty() {
+        return None;
+    }
+
+    Some(format!("(({}))", conditions.join(") OR (")))
+}
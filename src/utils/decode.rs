@@ -0,0 +1,216 @@
+use ethers::abi::{ethabi, ParamType, Token};
+use primitive_types::{H160, U256};
+use std::collections::HashSet;
+
+use crate::{
+    configs::Dataset,
+    db::{
+        models::{
+            decode_error::DatabaseDecodeError,
+            dex_trade::DatabaseDexTrade,
+            erc1155_transfer::DatabaseERC1155Transfer,
+            erc20_transfer::DatabaseERC20Transfer,
+            erc721_transfer::DatabaseERC721Transfer, log::DatabaseLog,
+        },
+        BlockFetchedData,
+    },
+    errors::IndexerError,
+    utils::events::{
+        ERC1155_TRANSFER_BATCH_EVENT_SIGNATURE,
+        ERC1155_TRANSFER_SINGLE_EVENT_SIGNATURE, SWAPV3_EVENT_SIGNATURE,
+        SWAP_EVENT_SIGNATURE, TRANSFER_EVENTS_SIGNATURE,
+    },
+};
+
+fn decode_hex(data: &str) -> Result<Vec<u8>, IndexerError> {
+    let without_prefix = data.strip_prefix("0x").unwrap_or(data);
+
+    hex::decode(without_prefix)
+        .map_err(|err| IndexerError::Decode(format!("{}: {}", data, err)))
+}
+
+// Decodes a single indexed value from its 32 bytes topic.
+pub fn decode_topic(
+    topic: Option<&str>,
+    kind: ParamType,
+) -> Result<Token, IndexerError> {
+    let topic = topic.ok_or_else(|| {
+        IndexerError::Decode("missing indexed topic".to_string())
+    })?;
+
+    let bytes = decode_hex(topic)?;
+
+    if bytes.len() != 32 {
+        return Err(IndexerError::Decode(format!(
+            "topic {} is not 32 bytes long",
+            topic
+        )));
+    }
+
+    ethabi::decode(&[kind], &bytes)?.pop().ok_or_else(|| {
+        IndexerError::Decode(format!("empty topic {}", topic))
+    })
+}
+
+pub fn decode_topic_address(
+    topic: Option<&str>,
+) -> Result<H160, IndexerError> {
+    token_address(decode_topic(topic, ParamType::Address)?)
+}
+
+// Decodes the non indexed values of a log.
+pub fn decode_data(
+    data: &str,
+    kinds: &[ParamType],
+) -> Result<Vec<Token>, IndexerError> {
+    let bytes = decode_hex(data)?;
+
+    Ok(ethabi::decode(kinds, &bytes)?)
+}
+
+pub fn token_address(token: Token) -> Result<H160, IndexerError> {
+    token.clone().into_address().ok_or_else(|| {
+        IndexerError::Decode(format!("{:?} is not an address", token))
+    })
+}
+
+pub fn token_uint(token: Token) -> Result<U256, IndexerError> {
+    token.clone().into_uint().ok_or_else(|| {
+        IndexerError::Decode(format!("{:?} is not an uint", token))
+    })
+}
+
+// Signed values are kept in their two's complement representation.
+pub fn token_int(token: Token) -> Result<U256, IndexerError> {
+    token.clone().into_int().ok_or_else(|| {
+        IndexerError::Decode(format!("{:?} is not an int", token))
+    })
+}
+
+pub fn token_uints(token: Token) -> Result<Vec<U256>, IndexerError> {
+    match token.clone().into_array() {
+        Some(tokens) => tokens.into_iter().map(token_uint).collect(),
+        None => Err(IndexerError::Decode(format!(
+            "{:?} is not an array",
+            token
+        ))),
+    }
+}
+
+// Derives the selected token transfers and dex trades from the logs of any source (receipts,
+// eth_getLogs or the logs table). Logs that match a known event but can't be decoded are
+// recorded as decode errors instead of failing the whole batch.
+pub fn decode_log_events(
+    logs: &[DatabaseLog],
+    datasets: &HashSet<Dataset>,
+) -> BlockFetchedData {
+    let mut events = BlockFetchedData::new();
+
+    for log in logs.iter() {
+        if let Err(err) = decode_log_event(log, datasets, &mut events) {
+            events
+                .decode_errors
+                .push(DatabaseDecodeError::from_log(log, &err));
+        }
+    }
+
+    events
+}
+
+fn decode_log_event(
+    log: &DatabaseLog,
+    datasets: &HashSet<Dataset>,
+    events: &mut BlockFetchedData,
+) -> Result<(), IndexerError> {
+    // Check the first topic matches the erc20, erc721, erc1155 or a swap signatures
+    let topic0 = log.topic0.as_str();
+
+    if topic0 == TRANSFER_EVENTS_SIGNATURE {
+        // Check if it is a erc20 or a erc721 based on the number of logs
+
+        // erc721 token transfer events have 3 indexed values.
+        if log.topic3.is_some() {
+            if datasets.contains(&Dataset::Erc721) {
+                events
+                    .erc721_transfers
+                    .push(DatabaseERC721Transfer::from_rpc(log)?);
+            }
+        } else if log.topic1.is_some()
+            && log.topic2.is_some()
+            && datasets.contains(&Dataset::Erc20)
+        {
+            // erc20 token transfer events have 2 indexed values.
+            events
+                .erc20_transfers
+                .push(DatabaseERC20Transfer::from_rpc(log)?);
+        }
+    }
+
+    if topic0 == ERC1155_TRANSFER_SINGLE_EVENT_SIGNATURE
+        && datasets.contains(&Dataset::Erc1155)
+        && log.topic1.is_some()
+        && log.topic2.is_some()
+        && log.topic3.is_some()
+    {
+        let mut transfer_values = decode_data(
+            &log.data,
+            &[ParamType::Uint(256), ParamType::Uint(256)],
+        )?
+        .into_iter();
+
+        let id = transfer_values.next().map(token_uint).transpose()?;
+        let amount = transfer_values.next().map(token_uint).transpose()?;
+
+        if let (Some(id), Some(amount)) = (id, amount) {
+            events.erc1155_transfers.push(
+                DatabaseERC1155Transfer::from_single_rpc(log, id, amount)?,
+            );
+        }
+    }
+
+    if topic0 == ERC1155_TRANSFER_BATCH_EVENT_SIGNATURE
+        && datasets.contains(&Dataset::Erc1155)
+        && log.topic1.is_some()
+        && log.topic2.is_some()
+        && log.topic3.is_some()
+    {
+        let mut transfer_values = decode_data(
+            &log.data,
+            &[
+                ParamType::Array(Box::new(ParamType::Uint(256))),
+                ParamType::Array(Box::new(ParamType::Uint(256))),
+            ],
+        )?
+        .into_iter();
+
+        let ids = transfer_values.next().map(token_uints).transpose()?;
+        let amounts =
+            transfer_values.next().map(token_uints).transpose()?;
+
+        if let (Some(ids), Some(amounts)) = (ids, amounts) {
+            events.erc1155_transfers.push(
+                DatabaseERC1155Transfer::from_batch_rpc(
+                    log, ids, amounts,
+                )?,
+            );
+        }
+    }
+
+    if topic0 == SWAP_EVENT_SIGNATURE
+        && datasets.contains(&Dataset::DexTrades)
+        && log.topic1.is_some()
+        && log.topic2.is_some()
+    {
+        events.dex_trades.push(DatabaseDexTrade::from_v2_rpc(log)?);
+    }
+
+    if topic0 == SWAPV3_EVENT_SIGNATURE
+        && datasets.contains(&Dataset::DexTrades)
+        && log.topic1.is_some()
+        && log.topic2.is_some()
+    {
+        events.dex_trades.push(DatabaseDexTrade::from_v3_rpc(log)?);
+    }
+
+    Ok(())
+}
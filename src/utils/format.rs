@@ -28,6 +28,23 @@ pub fn format_bytes_slice(b: &[u8]) -> String {
     format!("0x{}", hex::encode(b))
 }
 
+// Amounts written by hand in config files, as hex strings or decimal ones.
+pub fn deserialize_u256_str<'de, D>(
+    deserializer: D,
+) -> Result<U256, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+
+    match value.strip_prefix("0x") {
+        Some(hex_value) => U256::from_str_radix(hex_value, 16)
+            .map_err(|err| serde::de::Error::custom(format!("{:?}", err))),
+        None => U256::from_dec_str(&value)
+            .map_err(|err| serde::de::Error::custom(format!("{:?}", err))),
+    }
+}
+
 pub fn byte4_from_input(input: &str) -> [u8; 4] {
     let input_sanitized = input.strip_prefix("0x").unwrap();
 
@@ -1,2 +1,4 @@
+pub mod decode;
 pub mod events;
 pub mod format;
+pub mod shutdown;
@@ -0,0 +1,89 @@
+use log::{info, warn};
+use std::sync::Arc;
+use tokio::{signal, sync::watch};
+
+// Shared stop flag raised on SIGINT/SIGTERM. Fetch loops stop taking new blocks once it is raised
+// and the writers drain and commit what was already fetched before returning.
+#[derive(Debug, Clone)]
+pub struct Shutdown {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl Shutdown {
+    pub fn listen() -> Self {
+        let shutdown = Self { sender: Arc::new(watch::channel(false).0) };
+
+        tokio::spawn({
+            let shutdown = shutdown.clone();
+
+            async move {
+                wait_for_signal().await;
+
+                info!("Shutdown requested, committing fetched blocks before exiting.");
+
+                shutdown.request();
+
+                // A second signal skips the drain for operators that can't wait.
+                wait_for_signal().await;
+
+                warn!(
+                    "Shutdown forced, fetched blocks are not committed."
+                );
+
+                std::process::exit(1);
+            }
+        });
+
+        shutdown
+    }
+
+    // Flag raised along with this one, that can also be raised alone, e.g. to stop a single chain.
+    pub fn child(&self) -> Self {
+        let child = Self { sender: Arc::new(watch::channel(false).0) };
+
+        tokio::spawn({
+            let parent = self.clone();
+            let child = child.clone();
+
+            async move {
+                parent.requested().await;
+
+                child.request();
+            }
+        });
+
+        child
+    }
+
+    pub fn request(&self) {
+        self.sender.send_replace(true);
+    }
+
+    pub fn is_requested(&self) -> bool {
+        *self.sender.borrow()
+    }
+
+    // Resolves once the shutdown is requested.
+    pub async fn requested(&self) {
+        let mut receiver = self.sender.subscribe();
+
+        receiver.wait_for(|requested| *requested).await.ok();
+    }
+}
+
+async fn wait_for_signal() {
+    #[cfg(unix)]
+    {
+        let mut terminate =
+            signal::unix::signal(signal::unix::SignalKind::terminate())
+                .expect("unable to listen for SIGTERM");
+
+        tokio::select! {
+            _ = signal::ctrl_c() => (),
+            _ = terminate.recv() => (),
+        }
+    }
+
+    #[cfg(not(unix))]
+    signal::ctrl_c().await.ok();
+}
@@ -0,0 +1,52 @@
+#!/usr/bin/env bash
+# Records the eth_getBlockByNumber (with transactions) and eth_getBlockReceipts responses the block
+# golden tests of tests/models.rs run on. Each block is the first one from a fork height holding a
+# transaction of the type it covers, so the recordings can be made again from any archive node:
+#
+#   ETH_RPC=https://... OP_RPC=https://... tests/fixtures/record_blocks.sh
+#
+# Then run the tests with `cargo test --test models -- --include-ignored`.
+set -euo pipefail
+
+: "${ETH_RPC:?set ETH_RPC to an ethereum mainnet rpc with eth_getBlockReceipts}"
+: "${OP_RPC:?set OP_RPC to an OP mainnet rpc with eth_getBlockReceipts}"
+
+DIR="$(cd "$(dirname "$0")" && pwd)/blocks"
+mkdir -p "$DIR"
+
+rpc() {
+  local url=$1 method=$2 params=$3
+
+  curl -sf -X POST -H 'content-type: application/json' \
+    --data "{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"$method\",\"params\":$params}" \
+    "$url" | jq -e '.result'
+}
+
+# record <name> <rpc> <chain> <rollup or null> <first block> <transaction type>
+record() {
+  local name=$1 url=$2 chain=$3 rollup=$4 number=$5 type=$6 block
+
+  while true; do
+    block=$(rpc "$url" eth_getBlockByNumber "[\"$(printf '0x%x' "$number")\", true]")
+
+    if jq -e --arg type "$type" \
+      'any(.transactions[]; (.type // "0x0") == $type)' <<<"$block" >/dev/null; then
+      break
+    fi
+
+    number=$((number + 1))
+  done
+
+  rpc "$url" eth_getBlockReceipts "[\"$(printf '0x%x' "$number")\"]" |
+    jq --argjson block "$block" --argjson chain "$chain" --argjson rollup "$rollup" \
+      '{chain: $chain, rollup: $rollup, block: $block, receipts: .}' >"$DIR/$name.json"
+
+  echo "Recorded block $number as $name."
+}
+
+# A block before London (12,965,000), the first ones from Cancun and Prague with a blob and a set
+# code transaction, and an OP mainnet block, which always starts with its L1 attributes deposit.
+record pre_london "$ETH_RPC" 1 null 12000000 0x0
+record blob "$ETH_RPC" 1 null 19426587 0x3
+record set_code "$ETH_RPC" 1 null 22431084 0x4
+record op_deposit "$OP_RPC" 10 '"op_stack"' 120000000 0x7e
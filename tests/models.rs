@@ -0,0 +1,845 @@
+// Golden tests of the conversions from rpc responses into the database models. Each fixture holds
+// an `rpc` object, in the format returned by eth_getTransactionByHash, eth_getTransactionReceipt,
+// eth_getLogs or eth_getBlockByNumber (its withdrawals, or the block along with the `uncles`
+// returned by eth_getUncleByBlockNumberAndIndex), and the `expected` values of the model built
+// from it. The fixtures are written by hand in that format, their hashes and signatures aren't
+// those of real transactions and must not be used to check them. The recorded blocks of
+// tests/fixtures/blocks are real responses, checked by the ignored tests once recorded.
+
+use ethers::types::{
+    Block, Log, Transaction, TransactionReceipt, TxHash, Withdrawal,
+};
+use primitive_types::U256;
+use satschain_indexer::{
+    chains::{
+        rewards::{BlockReward, BlockRewardModel, ProofOfWork},
+        Rollup,
+    },
+    configs::Dataset,
+    db::models::{
+        authorization::DatabaseAuthorization,
+        block::DatabaseBlock,
+        dex_trade::DatabaseDexTrade,
+        erc1155_transfer::DatabaseERC1155Transfer,
+        erc20_transfer::DatabaseERC20Transfer,
+        erc721_transfer::DatabaseERC721Transfer,
+        log::DatabaseLog,
+        transaction::DatabaseTransaction,
+        uncle::DatabaseUncle,
+        withdrawal::{DatabaseWithdrawal, WithdrawalType},
+    },
+    utils::decode::decode_log_events,
+};
+use serde_json::Value;
+use std::fs;
+
+const CHAIN: u64 = 1;
+const TIMESTAMP: u32 = 1681338455;
+
+fn fixture(name: &str) -> Value {
+    let path = format!(
+        "{}/tests/fixtures/{}.json",
+        env!("CARGO_MANIFEST_DIR"),
+        name
+    );
+
+    let content = fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("unable to read {}: {}", path, err));
+
+    serde_json::from_str(&content)
+        .unwrap_or_else(|err| panic!("unable to parse {}: {}", path, err))
+}
+
+fn expected_str(expected: &Value, field: &str) -> String {
+    expected[field]
+        .as_str()
+        .unwrap_or_else(|| panic!("missing expected {}", field))
+        .to_string()
+}
+
+fn expected_u64(expected: &Value, field: &str) -> u64 {
+    expected[field]
+        .as_u64()
+        .unwrap_or_else(|| panic!("missing expected {}", field))
+}
+
+fn expected_u256(expected: &Value, field: &str) -> Option<U256> {
+    expected[field].as_str().map(|value| {
+        U256::from_dec_str(value)
+            .unwrap_or_else(|err| panic!("invalid {}: {}", field, err))
+    })
+}
+
+fn expected_u256s(expected: &Value, field: &str) -> Vec<U256> {
+    expected[field]
+        .as_array()
+        .unwrap_or_else(|| panic!("missing expected {}", field))
+        .iter()
+        .map(|value| U256::from_dec_str(value.as_str().unwrap()).unwrap())
+        .collect()
+}
+
+// Lists only set on some fixtures, e.g. the blob hashes of blob transactions.
+fn expected_strs(expected: &Value, field: &str) -> Vec<String> {
+    expected[field].as_array().map_or(Vec::new(), |values| {
+        values
+            .iter()
+            .map(|value| value.as_str().unwrap().to_string())
+            .collect()
+    })
+}
+
+fn load_transaction(name: &str) -> (DatabaseTransaction, Value) {
+    let fixture = fixture(name);
+
+    let transaction: Transaction =
+        serde_json::from_value(fixture["rpc"].clone()).unwrap();
+
+    (
+        DatabaseTransaction::from_rpc(&transaction, CHAIN, TIMESTAMP),
+        fixture["expected"].clone(),
+    )
+}
+
+fn load_log(name: &str) -> (DatabaseLog, Value) {
+    let fixture = fixture(name);
+
+    let log: Log = serde_json::from_value(fixture["rpc"].clone()).unwrap();
+
+    let block_number = log.block_number.unwrap().as_u32();
+
+    (
+        DatabaseLog::from_rpc(&log, CHAIN, TIMESTAMP, &block_number),
+        fixture["expected"].clone(),
+    )
+}
+
+fn assert_transaction(name: &str) {
+    let (transaction, expected) = load_transaction(name);
+
+    assert_eq!(transaction.hash, expected_str(&expected, "hash"));
+    assert_eq!(
+        transaction.block_number as u64,
+        expected_u64(&expected, "block_number")
+    );
+    assert_eq!(transaction.chain, CHAIN);
+    assert_eq!(transaction.from, expected_str(&expected, "from"));
+    assert_eq!(transaction.to, expected_str(&expected, "to"));
+    assert_eq!(transaction.gas as u64, expected_u64(&expected, "gas"));
+    assert_eq!(
+        transaction.gas_price,
+        expected_u256(&expected, "gas_price")
+    );
+    assert_eq!(
+        transaction.max_fee_per_blob_gas,
+        expected_u256(&expected, "max_fee_per_blob_gas")
+    );
+    assert_eq!(
+        transaction.max_fee_per_gas,
+        expected_u256(&expected, "max_fee_per_gas")
+    );
+    assert_eq!(
+        transaction.max_priority_fee_per_gas,
+        expected_u256(&expected, "max_priority_fee_per_gas")
+    );
+    assert_eq!(transaction.method, expected_str(&expected, "method"));
+    assert_eq!(transaction.mint, expected_u256(&expected, "mint"));
+    assert_eq!(transaction.nonce as u64, expected_u64(&expected, "nonce"));
+    assert_eq!(
+        transaction.source_hash,
+        expected["source_hash"].as_str().map(String::from)
+    );
+    assert_eq!(transaction.timestamp, TIMESTAMP);
+    assert_eq!(
+        transaction.transaction_index as u64,
+        expected_u64(&expected, "transaction_index")
+    );
+    assert_eq!(
+        transaction.transaction_type.clone() as u64,
+        expected_u64(&expected, "transaction_type")
+    );
+    assert_eq!(Some(transaction.value), expected_u256(&expected, "value"));
+
+    let access_list: Vec<(String, Vec<String>)> =
+        serde_json::from_value(expected["access_list"].clone()).unwrap();
+    assert_eq!(transaction.access_list, access_list);
+    assert_eq!(
+        transaction.blob_versioned_hashes,
+        expected_strs(&expected, "blob_versioned_hashes")
+    );
+
+    // Receipt data is only added later.
+    assert_eq!(transaction.gas_used, None);
+    assert_eq!(transaction.priority_fee, None);
+    assert_eq!(transaction.status, None);
+}
+
+fn assert_receipt_fees(name: &str) {
+    let fixture = fixture(name);
+
+    let receipt: TransactionReceipt =
+        serde_json::from_value(fixture["rpc"].clone()).unwrap();
+    let base_fee_per_gas = fixture["base_fee_per_gas"].as_u64();
+    let expected = &fixture["expected"];
+
+    // The receipt is added to its transaction when the fees depend on its type.
+    let mut transaction = match fixture["transaction"].as_str() {
+        Some(transaction) => load_transaction(transaction).0,
+        None => DatabaseTransaction::new(),
+    };
+    transaction.add_receipt_data(base_fee_per_gas, &receipt);
+
+    if let Some(rollup) = fixture.get("rollup") {
+        let rollup: Rollup =
+            serde_json::from_value(rollup.clone()).unwrap();
+        transaction.add_rollup_receipt_data(rollup, &receipt);
+    }
+
+    assert_eq!(
+        transaction.blob_gas_price,
+        expected_u256(expected, "blob_gas_price")
+    );
+    assert_eq!(
+        transaction
+            .blob_gas_used
+            .map(|blob_gas_used| blob_gas_used as u64),
+        expected["blob_gas_used"].as_u64()
+    );
+    assert_eq!(transaction.burned, expected_u256(expected, "burned"));
+    assert_eq!(
+        transaction.effective_transaction_fee,
+        expected_u256(expected, "effective_transaction_fee")
+    );
+    assert_eq!(
+        transaction.gas_used.map(|gas_used| gas_used as u64),
+        expected["gas_used"].as_u64()
+    );
+    assert_eq!(
+        transaction
+            .gas_used_for_l1
+            .map(|gas_used_for_l1| gas_used_for_l1 as u64),
+        expected["gas_used_for_l1"].as_u64()
+    );
+    assert_eq!(
+        transaction.l1_blob_base_fee,
+        expected_u256(expected, "l1_blob_base_fee")
+    );
+    assert_eq!(
+        transaction.l1_block_number,
+        expected["l1_block_number"].as_u64()
+    );
+    assert_eq!(transaction.l1_fee, expected_u256(expected, "l1_fee"));
+    assert_eq!(
+        transaction.l1_gas_price,
+        expected_u256(expected, "l1_gas_price")
+    );
+    assert_eq!(transaction.l1_gas_used, expected["l1_gas_used"].as_u64());
+    assert_eq!(
+        transaction.priority_fee,
+        expected_u256(expected, "priority_fee")
+    );
+}
+
+fn assert_log_fields(
+    expected: &Value,
+    address: &str,
+    block_number: u32,
+    log_index: u16,
+    removed: bool,
+    timestamp: u32,
+    transaction_hash: &str,
+) {
+    assert_eq!(address, expected_str(expected, "address"));
+    assert_eq!(
+        block_number as u64,
+        expected_u64(expected, "block_number")
+    );
+    assert_eq!(log_index as u64, expected_u64(expected, "log_index"));
+    assert_eq!(removed, expected["removed"].as_bool().unwrap());
+    assert_eq!(timestamp, TIMESTAMP);
+    assert_eq!(
+        transaction_hash,
+        expected_str(expected, "transaction_hash")
+    );
+}
+
+// Quantity of a recorded rpc response, zero when the field is missing.
+fn recorded_u256(recorded: &Value, field: &str) -> U256 {
+    recorded[field]
+        .as_str()
+        .map(|value| {
+            U256::from_str_radix(value.trim_start_matches("0x"), 16)
+                .unwrap_or_else(|err| panic!("invalid {}: {}", field, err))
+        })
+        .unwrap_or_default()
+}
+
+fn recorded_str(recorded: &Value, field: &str) -> String {
+    recorded[field]
+        .as_str()
+        .unwrap_or_else(|| panic!("missing recorded {}", field))
+        .to_string()
+}
+
+// Checks the models built from a block of tests/fixtures/blocks, recorded by
+// tests/fixtures/record_blocks.sh with its transactions and receipts, against the raw responses.
+fn assert_recorded_block(name: &str, transaction_type: u64) {
+    let recorded = fixture(&format!("blocks/{}", name));
+
+    let chain = recorded["chain"].as_u64().unwrap();
+    let rollup: Option<Rollup> =
+        serde_json::from_value(recorded["rollup"].clone()).unwrap();
+    let raw_block = &recorded["block"];
+    let raw_transactions = raw_block["transactions"].as_array().unwrap();
+    let raw_receipts = recorded["receipts"].as_array().unwrap();
+
+    let block: Block<Transaction> =
+        serde_json::from_value(raw_block.clone()).unwrap();
+    let receipts: Vec<TransactionReceipt> =
+        serde_json::from_value(recorded["receipts"].clone()).unwrap();
+
+    let database_block = DatabaseBlock::from_rpc(&block, chain, false);
+
+    assert_eq!(database_block.hash, recorded_str(raw_block, "hash"));
+    assert_eq!(
+        U256::from(database_block.number),
+        recorded_u256(raw_block, "number")
+    );
+    assert_eq!(
+        U256::from(database_block.gas_used),
+        recorded_u256(raw_block, "gasUsed")
+    );
+    assert_eq!(
+        database_block.transactions as usize,
+        raw_transactions.len()
+    );
+    assert_eq!(
+        database_block.base_fee_per_gas.map(U256::from),
+        raw_block["baseFeePerGas"]
+            .as_str()
+            .map(|_| recorded_u256(raw_block, "baseFeePerGas"))
+    );
+    assert_eq!(
+        database_block.blob_gas_used.map(U256::from),
+        raw_block["blobGasUsed"]
+            .as_str()
+            .map(|_| recorded_u256(raw_block, "blobGasUsed"))
+    );
+    assert_eq!(
+        database_block.parent_beacon_block_root,
+        raw_block["parentBeaconBlockRoot"].as_str().map(String::from)
+    );
+
+    assert!(
+        raw_transactions.iter().any(|transaction| {
+            recorded_u256(transaction, "type") == transaction_type.into()
+        }),
+        "no transaction of type {} in {}",
+        transaction_type,
+        name
+    );
+    assert_eq!(receipts.len(), raw_transactions.len());
+
+    let timestamp = database_block.timestamp;
+    let base_fee_per_gas = recorded_u256(raw_block, "baseFeePerGas");
+
+    for (((transaction, raw_transaction), receipt), raw_receipt) in block
+        .transactions
+        .iter()
+        .zip(raw_transactions)
+        .zip(&receipts)
+        .zip(raw_receipts)
+    {
+        let mut database_transaction =
+            DatabaseTransaction::from_rpc(transaction, chain, timestamp);
+
+        database_transaction
+            .add_receipt_data(database_block.base_fee_per_gas, receipt);
+
+        if let Some(rollup) = rollup {
+            database_transaction.add_rollup_receipt_data(rollup, receipt);
+        }
+
+        let hash = recorded_str(raw_transaction, "hash");
+        let raw_type = recorded_u256(raw_transaction, "type");
+
+        assert_eq!(database_transaction.hash, hash);
+        assert_eq!(recorded_str(raw_receipt, "transactionHash"), hash);
+        assert_eq!(
+            database_transaction.from,
+            recorded_str(raw_transaction, "from")
+        );
+        assert_eq!(
+            U256::from(database_transaction.nonce),
+            recorded_u256(raw_transaction, "nonce")
+        );
+        assert_eq!(
+            U256::from(
+                database_transaction.transaction_type.clone() as u64
+            ),
+            raw_type
+        );
+        assert_eq!(
+            database_transaction.blob_versioned_hashes,
+            raw_transaction["blobVersionedHashes"]
+                .as_array()
+                .map(|hashes| {
+                    hashes
+                        .iter()
+                        .map(|hash| hash.as_str().unwrap().to_string())
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        );
+
+        let gas_used = recorded_u256(raw_receipt, "gasUsed");
+        let gas_price = recorded_u256(raw_receipt, "effectiveGasPrice");
+        let blob_fee = recorded_u256(raw_receipt, "blobGasUsed")
+            * recorded_u256(raw_receipt, "blobGasPrice");
+
+        // Deposits are paid for on L1 and burn no base fee.
+        let burned = match raw_type == U256::from(0x7e) {
+            true => blob_fee,
+            false => gas_used * base_fee_per_gas + blob_fee,
+        };
+
+        assert_eq!(
+            database_transaction.gas_used.map(U256::from),
+            Some(gas_used)
+        );
+        assert_eq!(
+            database_transaction.effective_transaction_fee,
+            Some(
+                gas_used * gas_price
+                    + blob_fee
+                    + recorded_u256(raw_receipt, "l1Fee")
+            )
+        );
+        assert_eq!(
+            database_transaction.priority_fee,
+            Some(gas_used * gas_price.saturating_sub(base_fee_per_gas))
+        );
+        assert_eq!(database_transaction.burned, Some(burned));
+
+        let authorizations =
+            DatabaseAuthorization::from_rpc(transaction, chain, timestamp);
+        let raw_authorizations = raw_transaction["authorizationList"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+
+        assert_eq!(authorizations.len(), raw_authorizations.len());
+
+        for (authorization, raw_authorization) in
+            authorizations.iter().zip(&raw_authorizations)
+        {
+            assert_eq!(
+                authorization.address,
+                recorded_str(raw_authorization, "address")
+            );
+            assert_eq!(
+                U256::from(authorization.nonce),
+                recorded_u256(raw_authorization, "nonce")
+            );
+            assert_eq!(authorization.transaction_hash, hash);
+        }
+    }
+}
+
+#[test]
+fn legacy_transaction() {
+    assert_transaction("transaction_legacy");
+}
+
+#[test]
+fn untyped_transaction_is_legacy() {
+    assert_transaction("transaction_untyped");
+}
+
+#[test]
+fn access_list_transaction() {
+    assert_transaction("transaction_access_list");
+}
+
+#[test]
+fn eip1559_transaction() {
+    assert_transaction("transaction_eip1559");
+}
+
+#[test]
+fn blob_transaction() {
+    assert_transaction("transaction_blob");
+}
+
+#[test]
+fn set_code_transaction() {
+    assert_transaction("transaction_set_code");
+
+    let fixture = fixture("transaction_set_code");
+    let transaction: Transaction =
+        serde_json::from_value(fixture["rpc"].clone()).unwrap();
+
+    let authorizations =
+        DatabaseAuthorization::from_rpc(&transaction, CHAIN, TIMESTAMP);
+    let expected =
+        fixture["expected"]["authorizations"].as_array().unwrap();
+
+    assert_eq!(authorizations.len(), expected.len());
+
+    for (i, (authorization, expected)) in
+        authorizations.iter().zip(expected.iter()).enumerate()
+    {
+        assert_eq!(
+            authorization.address,
+            expected_str(expected, "address")
+        );
+        assert_eq!(
+            authorization.authority,
+            expected["authority"].as_str().map(String::from)
+        );
+        assert_eq!(authorization.authorization_index as usize, i);
+        assert_eq!(
+            Some(authorization.chain_id),
+            expected_u256(expected, "chain_id")
+        );
+        assert_eq!(
+            authorization.is_revocation(),
+            expected["is_revocation"].as_bool().unwrap()
+        );
+        assert_eq!(authorization.nonce, expected_u64(expected, "nonce"));
+        assert_eq!(
+            authorization.transaction_hash,
+            expected_str(&fixture["expected"], "hash")
+        );
+    }
+}
+
+#[test]
+fn deposit_transaction() {
+    assert_transaction("transaction_deposit");
+}
+
+#[test]
+fn deposit_receipt() {
+    assert_receipt_fees("receipt_deposit");
+}
+
+#[test]
+fn op_stack_receipt() {
+    assert_receipt_fees("receipt_op_stack");
+}
+
+#[test]
+fn arbitrum_receipt() {
+    assert_receipt_fees("receipt_arbitrum");
+}
+
+#[test]
+fn blob_receipt() {
+    assert_receipt_fees("receipt_blob");
+}
+
+#[test]
+fn eip1559_receipt() {
+    assert_receipt_fees("receipt_eip1559");
+}
+
+#[test]
+fn pre_london_receipt() {
+    assert_receipt_fees("receipt_pre_london");
+}
+
+#[test]
+fn withdrawals() {
+    let fixture = fixture("withdrawals");
+    let block_number = expected_u64(&fixture, "block_number") as u32;
+
+    let withdrawals: Vec<Withdrawal> =
+        serde_json::from_value(fixture["rpc"].clone()).unwrap();
+    let expected = fixture["expected"].as_array().unwrap();
+
+    assert_eq!(withdrawals.len(), expected.len());
+
+    for (withdrawal, expected) in withdrawals.iter().zip(expected.iter()) {
+        let withdrawal = DatabaseWithdrawal::from_rpc(
+            withdrawal,
+            CHAIN,
+            block_number,
+            TIMESTAMP,
+        );
+
+        assert_eq!(withdrawal.address, expected_str(expected, "address"));
+        assert_eq!(
+            Some(withdrawal.amount),
+            expected_u256(expected, "amount")
+        );
+        assert_eq!(withdrawal.block_number, block_number);
+        assert_eq!(
+            withdrawal.validator_index as u64,
+            expected_u64(expected, "validator_index")
+        );
+        assert_eq!(
+            withdrawal.withdrawal_index as u64,
+            expected_u64(expected, "withdrawal_index")
+        );
+        assert_eq!(
+            withdrawal.withdrawal_type,
+            match expected_str(expected, "withdrawal_type").as_str() {
+                "full" => WithdrawalType::Full,
+                _ => WithdrawalType::Partial,
+            }
+        );
+    }
+}
+
+#[test]
+fn uncles_are_linked_to_their_nephew() {
+    let fixture = fixture("block_uncle");
+    let expected = &fixture["expected"];
+
+    let block: Block<TxHash> =
+        serde_json::from_value(fixture["rpc"].clone()).unwrap();
+    let mut nephew = DatabaseBlock::from_rpc(&block, CHAIN, false);
+
+    let mut uncles: Vec<DatabaseBlock> = fixture["uncles"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|uncle| {
+            let uncle: Block<TxHash> =
+                serde_json::from_value(uncle.clone()).unwrap();
+            DatabaseBlock::from_rpc(&uncle, CHAIN, true)
+        })
+        .collect();
+
+    let reward_model = ProofOfWork::ethereum();
+
+    nephew.add_rewards(
+        &reward_model.block_reward(&nephew, None, &uncles),
+        U256::zero(),
+    );
+    assert_eq!(
+        Some(nephew.uncle_rewards),
+        expected_u256(expected, "uncle_rewards")
+    );
+
+    let expected = expected["uncles"].as_array().unwrap();
+    assert_eq!(uncles.len(), expected.len());
+
+    for (uncle_index, (uncle, expected)) in
+        uncles.iter_mut().zip(expected.iter()).enumerate()
+    {
+        let uncle_reward = BlockReward {
+            base_reward: reward_model.uncle_reward(uncle, nephew.number),
+            ..Default::default()
+        };
+        uncle.add_rewards(&uncle_reward, U256::zero());
+
+        let uncle = DatabaseUncle::new(&nephew, uncle, uncle_index as u8);
+
+        assert_eq!(uncle.block_hash, expected_str(expected, "block_hash"));
+        assert_eq!(
+            uncle.block_number as u64,
+            expected_u64(expected, "block_number")
+        );
+        assert_eq!(uncle.hash, expected_str(expected, "hash"));
+        assert_eq!(
+            uncle.inclusion_distance as u64,
+            expected_u64(expected, "inclusion_distance")
+        );
+        assert_eq!(uncle.miner, expected_str(expected, "miner"));
+        assert_eq!(uncle.number as u64, expected_u64(expected, "number"));
+        assert_eq!(Some(uncle.reward), expected_u256(expected, "reward"));
+        assert_eq!(
+            uncle.uncle_index as u64,
+            expected_u64(expected, "uncle_index")
+        );
+    }
+}
+
+#[test]
+fn erc20_transfer() {
+    let (log, expected) = load_log("log_erc20_transfer");
+
+    let transfer = DatabaseERC20Transfer::from_rpc(&log).unwrap();
+
+    assert_log_fields(
+        &expected,
+        &transfer.address,
+        transfer.block_number,
+        transfer.log_index,
+        transfer.removed,
+        transfer.timestamp,
+        &transfer.transaction_hash,
+    );
+    assert_eq!(transfer.chain, CHAIN);
+    assert_eq!(transfer.from, expected_str(&expected, "from"));
+    assert_eq!(transfer.to, expected_str(&expected, "to"));
+    assert_eq!(Some(transfer.amount), expected_u256(&expected, "amount"));
+    assert_eq!(
+        transfer.token_address,
+        expected_str(&expected, "token_address")
+    );
+}
+
+#[test]
+fn erc721_transfer() {
+    let (log, expected) = load_log("log_erc721_transfer");
+
+    let transfer = DatabaseERC721Transfer::from_rpc(&log).unwrap();
+
+    assert_log_fields(
+        &expected,
+        &transfer.address,
+        transfer.block_number,
+        transfer.log_index,
+        transfer.removed,
+        transfer.timestamp,
+        &transfer.transaction_hash,
+    );
+    assert_eq!(transfer.chain, CHAIN);
+    assert_eq!(transfer.from, expected_str(&expected, "from"));
+    assert_eq!(transfer.to, expected_str(&expected, "to"));
+    assert_eq!(Some(transfer.id), expected_u256(&expected, "id"));
+    assert_eq!(
+        transfer.token_address,
+        expected_str(&expected, "token_address")
+    );
+}
+
+fn assert_erc1155_transfer(
+    transfer: &DatabaseERC1155Transfer,
+    expected: &Value,
+) {
+    assert_log_fields(
+        expected,
+        &transfer.address,
+        transfer.block_number,
+        transfer.log_index,
+        transfer.removed,
+        transfer.timestamp,
+        &transfer.transaction_hash,
+    );
+    assert_eq!(transfer.chain, CHAIN);
+    assert_eq!(transfer.operator, expected_str(expected, "operator"));
+    assert_eq!(transfer.from, expected_str(expected, "from"));
+    assert_eq!(transfer.to, expected_str(expected, "to"));
+    assert_eq!(transfer.ids, expected_u256s(expected, "ids"));
+    assert_eq!(transfer.amounts, expected_u256s(expected, "amounts"));
+    assert_eq!(
+        transfer.token_address,
+        expected_str(expected, "token_address")
+    );
+}
+
+fn assert_dex_trade(trade: &DatabaseDexTrade, expected: &Value) {
+    assert_log_fields(
+        expected,
+        &trade.address,
+        trade.block_number,
+        trade.log_index,
+        trade.removed,
+        trade.timestamp,
+        &trade.transaction_hash,
+    );
+    assert_eq!(trade.chain, CHAIN);
+    assert_eq!(trade.maker, expected_str(expected, "maker"));
+    assert_eq!(trade.receiver, expected_str(expected, "receiver"));
+    assert_eq!(trade.pair, expected_str(expected, "pair"));
+    assert_eq!(
+        Some(trade.token0_amount),
+        expected_u256(expected, "token0_amount")
+    );
+    assert_eq!(
+        Some(trade.token1_amount),
+        expected_u256(expected, "token1_amount")
+    );
+}
+
+#[test]
+fn erc1155_transfer_single() {
+    let (log, expected) = load_log("log_erc1155_transfer_single");
+
+    let events = decode_log_events(&[log], &Dataset::all());
+
+    assert!(events.decode_errors.is_empty());
+    assert_eq!(events.erc1155_transfers.len(), 1);
+    assert_erc1155_transfer(&events.erc1155_transfers[0], &expected);
+}
+
+#[test]
+fn erc1155_transfer_batch() {
+    let (log, expected) = load_log("log_erc1155_transfer_batch");
+
+    let events = decode_log_events(&[log], &Dataset::all());
+
+    assert!(events.decode_errors.is_empty());
+    assert_eq!(events.erc1155_transfers.len(), 1);
+    assert_erc1155_transfer(&events.erc1155_transfers[0], &expected);
+}
+
+#[test]
+fn uniswap_v2_swap() {
+    let (log, expected) = load_log("log_swap_v2");
+
+    let trade = DatabaseDexTrade::from_v2_rpc(&log).unwrap();
+
+    assert_dex_trade(&trade, &expected);
+}
+
+#[test]
+fn uniswap_v3_swap() {
+    let (log, expected) = load_log("log_swap_v3");
+
+    let trade = DatabaseDexTrade::from_v3_rpc(&log).unwrap();
+
+    assert_dex_trade(&trade, &expected);
+}
+
+#[test]
+fn transfer_logs_are_told_apart_by_their_topics() {
+    let (erc20_log, _) = load_log("log_erc20_transfer");
+    let (erc721_log, _) = load_log("log_erc721_transfer");
+
+    let events =
+        decode_log_events(&[erc20_log, erc721_log], &Dataset::all());
+
+    assert!(events.decode_errors.is_empty());
+    assert_eq!(events.erc20_transfers.len(), 1);
+    assert_eq!(events.erc721_transfers.len(), 1);
+}
+
+#[test]
+fn undecodable_log_is_recorded() {
+    let (mut log, _) = load_log("log_erc20_transfer");
+    log.data = String::from("0x1234");
+
+    let events = decode_log_events(&[log], &Dataset::all());
+
+    assert!(events.erc20_transfers.is_empty());
+    assert_eq!(events.decode_errors.len(), 1);
+}
+
+#[test]
+#[ignore = "needs the recordings of tests/fixtures/record_blocks.sh"]
+fn recorded_pre_london_block() {
+    assert_recorded_block("pre_london", 0x0);
+}
+
+#[test]
+#[ignore = "needs the recordings of tests/fixtures/record_blocks.sh"]
+fn recorded_blob_block() {
+    assert_recorded_block("blob", 0x3);
+}
+
+#[test]
+#[ignore = "needs the recordings of tests/fixtures/record_blocks.sh"]
+fn recorded_set_code_block() {
+    assert_recorded_block("set_code", 0x4);
+}
+
+#[test]
+#[ignore = "needs the recordings of tests/fixtures/record_blocks.sh"]
+fn recorded_op_deposit_block() {
+    assert_recorded_block("op_deposit", 0x7e);
+}